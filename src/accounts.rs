@@ -1,287 +1,437 @@
-pub fn get_accounts() -> Vec<String> {
-    [
-        (
-            "dp2waEWSBy5yKmq65ergoU3G6qRLmqa6K7We4rZSKph",
-            "dx",
-            "Dradex",
-        ),
-        (
-            "7WduLbRfYhTJktjLw5FDEyrqoEv61aTTCuGAetgLjzN5",
-            "gz",
-            "GooseFX",
-        ),
-        (
-            "cysPXAjehMpVKUapzbMCCnpFxUFFryEWEaLgnb9NrR8",
-            "ck",
-            "Cykura",
-        ),
-        (
-            "EewxydAPCCVuNEyrVN68PuSYdQ7wKn27V9Gjeoi8dy3S",
-            "ln",
-            "Lifinity",
-        ),
-        (
-            "C1onEW2kPetmHmwe74YC1ESx3LnFEpVau6g2pg4fHycr",
-            "cn",
-            "Clone",
-        ),
-        (
-            "D3BBjqUdCYuP18fNvvMbPAZ8DpcRi4io2EsYHQawJDag",
-            "bs",
-            "sentre",
-        ),
-        (
-            "GFXsSL5sSaDfNFQUYsHekbWBW1TsFdjDYzACh62tEHxn",
-            "g2",
-            "GooseFX v2",
-        ),
-        ("HyhpEq587ANShDdbx1mP4dTmDZC44CXWft29oYQXDb53", "Fx", "Fox"),
-        (
-            "DjVE6JNiYqPL2QXyCUUh8rNjHrbz9hXHNYt99MQ59qw1",
-            "o1",
-            "Orca v1",
-        ),
-        ("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", "o2", "Orca"),
-        (
-            "MERLuDFBMmsHnsBPZw2sDQZHvXFMwp8EdjudcU2HKky",
-            "mr",
-            "Mercurial",
-        ),
-        (
-            "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
-            "rm",
-            "Serum",
-        ),
-        ("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ", "br", "Saber"),
-        (
-            "PSwapMdSai8tjrEXcxFeQth87xC4rRsa4VA5mhGhXkP",
-            "pg",
-            "Penguin",
-        ),
-        (
-            "AMM55ShdkoGRB5jVYPjWziwk8m5MpwyDgsMWHaMSQWH6",
-            "a1",
-            "Aldrin",
-        ),
-        (
-            "CURVGoZn8zycx6FXwwevgBTB2gVvdbGTEpvMJDbgs2t4",
-            "a2",
-            "Aldrin v2",
-        ),
-        ("SSwpMgqNDsyV7mAgN9ady4bDVu5ySjmmXejXvy2vLt1", "tp", "Step"),
-        (
-            "CTMAxxk34HjKWxQ3QLZK1HpaLXmBveao3ESePXbiyfzh",
-            "cp",
-            "Cropper",
-        ),
-        (
-            "SCHAtsf8mbjyjiv4LkhLKutTf6JnZAbdJKFkXQNMFHZ",
-            "nh",
-            "Sencha",
-        ),
-        (
-            "CLMM9tUoggJu2wagPkkqs9eFG4BWhVBZWkP1qv3Sp7tR",
-            "cm",
-            "Crema",
-        ),
-        ("SSwapUtytfBdBn1b9NUGG6foMVPtcWgpRU32HToDUZr", "ss", "Saros"),
-        (
-            "MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD",
-            "md",
-            "Marinade",
-        ),
-        (
-            "Dooar9JkhdZ7J3LHN3A7YCuoGRUggXhQaG4kijfLGU2j",
-            "pn",
-            "Stepn",
-        ),
-        (
-            "HyaB3W9q6XdA5xwpU4XnSZV94htfmbmqJXZcEbRaJutt",
-            "iv",
-            "Invariant",
-        ),
-        (
-            "DecZY86MU5Gj7kppfUCEmd4LbXXuyZH1yHaP2NTqdiZB",
-            "dw",
-            "Saber Decimal Wrapper",
-        ),
-        (
-            "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX",
-            "O",
-            "Openbook",
-        ),
-        (
-            "9tKE7Mbmj4mxDjWatikzGAtkoWosiiZX9y6J4Hfm2R8H",
-            "mp",
-            "Marco Polo",
-        ),
-        (
-            "2KehYt3KsEQR53jYcxjbQp2d2kCp4AkuQW68atufRwSr",
-            "ym",
-            "Symmetry",
-        ),
-        (
-            "BSwp6bEBihVLdqJRKGgzjcGLHkcTuzmSo1TQkHepzH8p",
-            "bk",
-            "BonkSwap",
-        ),
-        (
-            "treaf4wWBBty3fHdyBpo35Mz84M8k3heKXmjmi9vFt5",
-            "hn",
-            "Helium Network",
-        ),
-        (
-            "stkitrT1Uoy18Dk1fTrgPw8W6MVzoCfYoAFT4MLsmhq",
-            "ut",
-            "unstake.it",
-        ),
-        (
-            "SwaPpA9LAaLfeLi3a68M4DjnLqgtticKg6CnyNwgAC8",
-            "TS",
-            "Token Swap",
-        ),
-        (
-            "DSwpgjMvXhtGn6BsbqmacdBZyfLj6jSWf3HJpdJtmg6N",
-            "dl",
-            "Dexlab",
-        ),
-        (
-            "H8W3ctz92svYg6mkn1UtGfu2aQr2fnUFHM1RhScEtQDt",
-            "CW",
-            "Cropper Whirlpool",
-        ),
-        (
-            "5ocnV1qiCgaQR8Jb8xWnVbApfaygJ8tNoZfgPwsgx9kx",
-            "Sn",
-            "Sanctum S",
-        ),
-        (
-            "Gswppe6ERWKpUTXvRPfXdzHhiCyJvLadVvXGfdpBqcE1",
-            "GS",
-            "GuacSwap",
-        ),
-        ("DEXYosS6oEGvk8uCDayvwEZz4qEyDJRf9nFgYCaqPMTm", "1x", "1DEX"),
-        ("PERPHjGBqRHArX4DySjwM6UJHiR3sWAatqfdBS2qQJu", "ps", "Perps"),
-        ("obriQD1zbpyLz95G5n7nJe6a4DPjpFwa5XYPoNm113y", "ob", "Obric"),
-        (
-            "FLUXubRmkEi2q6K3Y9kBPg9248ggaZVsoSFhtJHSrm1X",
-            "FB",
-            "FluxBeam",
-        ),
-        (
-            "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
-            "M",
-            "Meteora",
-        ),
-        (
-            "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c",
-            "L2",
-            "Lifinity v2",
-        ),
-        (
-            "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
-            "MD",
-            "Meteora DLMM",
-        ),
-        (
-            "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
-            "RC",
-            "Raydium CLMM",
-        ),
-        (
-            "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
-            "W",
-            "Whirlpool",
-        ),
-        ("SoLFiHG9TfgtdUXUjWAxi3LtvYuFyDLVhBWxdMZxyCe", "SF", "SolFi"),
-        (
-            "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
-            "RP",
-            "Raydium CP",
-        ),
-        (
-            "opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb",
-            "O2",
-            "Openbook v2",
-        ),
-        (
-            "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
-            "Ph",
-            "Phoenix",
-        ),
-        (
-            "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
-            "Pp",
-            "Pump.Fun",
-        ),
-        (
-            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
-            "JUP",
-            "jup v6",
-        ),
-        (
-            "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
-            "R",
-            "Raydium",
-        ),
-        (
-            "MR2LqxoSbw831bNy68utpu5n4YqBH3AzDmddkgk9LQv",
-            "ms",
-            "Marinade stacking",
-        ),
-        (
-            "swapNyd8XiQwJ6ianp9snpu4brUqFxadzvHebnAXjJZ",
-            "st",
-            "StableSwap",
-        ),
-        (
-            "swapFpHZwjELNnjvThjajtiVmkz3yPQEHjLtka2fwHW",
-            "sw",
-            "StableWeighted",
-        ),
-        (
-            "5quBtoiQqxF9Jv6KYKctB59NT3gtJD2Y65kdnB1Uev3h",
-            "Rl",
-            "Raydium Liquid",
-        ),
-        ("ZERor4xhbUycZ6gb9ntrhqscUcZmAbQDjEAtCf4hbZY", "Z", "ZeroFi"),
-        (
-            "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG",
-            "mn",
-            "Moonshot",
-        ),
-        (
-            "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
-            "pA",
-            "PumpFun new",
-        ),
-        (
-            "GAMMA7meSFWaBXF25oSUgmGRwaW6sCMFLmBNiMSdbHVT",
-            "Gm",
-            "Gamma amm",
-        ),
-        (
-            "NUMERUNsFCP3kuNmWZuXtm1AaQCPj9uw6Guv2Ekoi5P",
-            "nm",
-            "numeraire",
-        ),
-        (
-            "1MooN32fuBBgApc8ujknKJw5sef3BVwPGgz3pto1BAh",
-            "MU",
-            "new moon",
-        ),
-        (
-            "WooFif76YGRNjk1pA8wCsN67aQsD9f9iLsz4NcJ1AVb",
-            "wf",
-            "woofie",
-        ),
-        (
-            "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj",
-            "lp",
-            "Launchpad Ray",
-        ),
-    ]
-    .iter()
-    .map(|(program_id, _, _)| program_id.to_string())
-    .collect()
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Встроенный список DEX программ: (program id, короткий код, название)
+const BUILTIN_PROGRAMS: &[(&str, &str, &str)] = &[
+    (
+        "dp2waEWSBy5yKmq65ergoU3G6qRLmqa6K7We4rZSKph",
+        "dx",
+        "Dradex",
+    ),
+    (
+        "7WduLbRfYhTJktjLw5FDEyrqoEv61aTTCuGAetgLjzN5",
+        "gz",
+        "GooseFX",
+    ),
+    (
+        "cysPXAjehMpVKUapzbMCCnpFxUFFryEWEaLgnb9NrR8",
+        "ck",
+        "Cykura",
+    ),
+    (
+        "EewxydAPCCVuNEyrVN68PuSYdQ7wKn27V9Gjeoi8dy3S",
+        "ln",
+        "Lifinity",
+    ),
+    (
+        "C1onEW2kPetmHmwe74YC1ESx3LnFEpVau6g2pg4fHycr",
+        "cn",
+        "Clone",
+    ),
+    (
+        "D3BBjqUdCYuP18fNvvMbPAZ8DpcRi4io2EsYHQawJDag",
+        "bs",
+        "sentre",
+    ),
+    (
+        "GFXsSL5sSaDfNFQUYsHekbWBW1TsFdjDYzACh62tEHxn",
+        "g2",
+        "GooseFX v2",
+    ),
+    ("HyhpEq587ANShDdbx1mP4dTmDZC44CXWft29oYQXDb53", "Fx", "Fox"),
+    (
+        "DjVE6JNiYqPL2QXyCUUh8rNjHrbz9hXHNYt99MQ59qw1",
+        "o1",
+        "Orca v1",
+    ),
+    ("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", "o2", "Orca"),
+    (
+        "MERLuDFBMmsHnsBPZw2sDQZHvXFMwp8EdjudcU2HKky",
+        "mr",
+        "Mercurial",
+    ),
+    (
+        "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+        "rm",
+        "Serum",
+    ),
+    ("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ", "br", "Saber"),
+    (
+        "PSwapMdSai8tjrEXcxFeQth87xC4rRsa4VA5mhGhXkP",
+        "pg",
+        "Penguin",
+    ),
+    (
+        "AMM55ShdkoGRB5jVYPjWziwk8m5MpwyDgsMWHaMSQWH6",
+        "a1",
+        "Aldrin",
+    ),
+    (
+        "CURVGoZn8zycx6FXwwevgBTB2gVvdbGTEpvMJDbgs2t4",
+        "a2",
+        "Aldrin v2",
+    ),
+    ("SSwpMgqNDsyV7mAgN9ady4bDVu5ySjmmXejXvy2vLt1", "tp", "Step"),
+    (
+        "CTMAxxk34HjKWxQ3QLZK1HpaLXmBveao3ESePXbiyfzh",
+        "cp",
+        "Cropper",
+    ),
+    (
+        "SCHAtsf8mbjyjiv4LkhLKutTf6JnZAbdJKFkXQNMFHZ",
+        "nh",
+        "Sencha",
+    ),
+    (
+        "CLMM9tUoggJu2wagPkkqs9eFG4BWhVBZWkP1qv3Sp7tR",
+        "cm",
+        "Crema",
+    ),
+    ("SSwapUtytfBdBn1b9NUGG6foMVPtcWgpRU32HToDUZr", "ss", "Saros"),
+    (
+        "MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD",
+        "md",
+        "Marinade",
+    ),
+    (
+        "Dooar9JkhdZ7J3LHN3A7YCuoGRUggXhQaG4kijfLGU2j",
+        "pn",
+        "Stepn",
+    ),
+    (
+        "HyaB3W9q6XdA5xwpU4XnSZV94htfmbmqJXZcEbRaJutt",
+        "iv",
+        "Invariant",
+    ),
+    (
+        "DecZY86MU5Gj7kppfUCEmd4LbXXuyZH1yHaP2NTqdiZB",
+        "dw",
+        "Saber Decimal Wrapper",
+    ),
+    (
+        "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX",
+        "O",
+        "Openbook",
+    ),
+    (
+        "9tKE7Mbmj4mxDjWatikzGAtkoWosiiZX9y6J4Hfm2R8H",
+        "mp",
+        "Marco Polo",
+    ),
+    (
+        "2KehYt3KsEQR53jYcxjbQp2d2kCp4AkuQW68atufRwSr",
+        "ym",
+        "Symmetry",
+    ),
+    (
+        "BSwp6bEBihVLdqJRKGgzjcGLHkcTuzmSo1TQkHepzH8p",
+        "bk",
+        "BonkSwap",
+    ),
+    (
+        "treaf4wWBBty3fHdyBpo35Mz84M8k3heKXmjmi9vFt5",
+        "hn",
+        "Helium Network",
+    ),
+    (
+        "stkitrT1Uoy18Dk1fTrgPw8W6MVzoCfYoAFT4MLsmhq",
+        "ut",
+        "unstake.it",
+    ),
+    (
+        "SwaPpA9LAaLfeLi3a68M4DjnLqgtticKg6CnyNwgAC8",
+        "TS",
+        "Token Swap",
+    ),
+    (
+        "DSwpgjMvXhtGn6BsbqmacdBZyfLj6jSWf3HJpdJtmg6N",
+        "dl",
+        "Dexlab",
+    ),
+    (
+        "H8W3ctz92svYg6mkn1UtGfu2aQr2fnUFHM1RhScEtQDt",
+        "CW",
+        "Cropper Whirlpool",
+    ),
+    (
+        "5ocnV1qiCgaQR8Jb8xWnVbApfaygJ8tNoZfgPwsgx9kx",
+        "Sn",
+        "Sanctum S",
+    ),
+    (
+        "Gswppe6ERWKpUTXvRPfXdzHhiCyJvLadVvXGfdpBqcE1",
+        "GS",
+        "GuacSwap",
+    ),
+    ("DEXYosS6oEGvk8uCDayvwEZz4qEyDJRf9nFgYCaqPMTm", "1x", "1DEX"),
+    ("PERPHjGBqRHArX4DySjwM6UJHiR3sWAatqfdBS2qQJu", "ps", "Perps"),
+    ("obriQD1zbpyLz95G5n7nJe6a4DPjpFwa5XYPoNm113y", "ob", "Obric"),
+    (
+        "FLUXubRmkEi2q6K3Y9kBPg9248ggaZVsoSFhtJHSrm1X",
+        "FB",
+        "FluxBeam",
+    ),
+    (
+        "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
+        "M",
+        "Meteora",
+    ),
+    (
+        "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c",
+        "L2",
+        "Lifinity v2",
+    ),
+    (
+        "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
+        "MD",
+        "Meteora DLMM",
+    ),
+    (
+        "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
+        "RC",
+        "Raydium CLMM",
+    ),
+    (
+        "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+        "W",
+        "Whirlpool",
+    ),
+    ("SoLFiHG9TfgtdUXUjWAxi3LtvYuFyDLVhBWxdMZxyCe", "SF", "SolFi"),
+    (
+        "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
+        "RP",
+        "Raydium CP",
+    ),
+    (
+        "opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb",
+        "O2",
+        "Openbook v2",
+    ),
+    (
+        "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+        "Ph",
+        "Phoenix",
+    ),
+    (
+        "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
+        "Pp",
+        "Pump.Fun",
+    ),
+    (
+        "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+        "JUP",
+        "jup v6",
+    ),
+    (
+        "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+        "R",
+        "Raydium",
+    ),
+    (
+        "MR2LqxoSbw831bNy68utpu5n4YqBH3AzDmddkgk9LQv",
+        "ms",
+        "Marinade stacking",
+    ),
+    (
+        "swapNyd8XiQwJ6ianp9snpu4brUqFxadzvHebnAXjJZ",
+        "st",
+        "StableSwap",
+    ),
+    (
+        "swapFpHZwjELNnjvThjajtiVmkz3yPQEHjLtka2fwHW",
+        "sw",
+        "StableWeighted",
+    ),
+    (
+        "5quBtoiQqxF9Jv6KYKctB59NT3gtJD2Y65kdnB1Uev3h",
+        "Rl",
+        "Raydium Liquid",
+    ),
+    ("ZERor4xhbUycZ6gb9ntrhqscUcZmAbQDjEAtCf4hbZY", "Z", "ZeroFi"),
+    (
+        "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG",
+        "mn",
+        "Moonshot",
+    ),
+    (
+        "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
+        "pA",
+        "PumpFun new",
+    ),
+    (
+        "GAMMA7meSFWaBXF25oSUgmGRwaW6sCMFLmBNiMSdbHVT",
+        "Gm",
+        "Gamma amm",
+    ),
+    (
+        "NUMERUNsFCP3kuNmWZuXtm1AaQCPj9uw6Guv2Ekoi5P",
+        "nm",
+        "numeraire",
+    ),
+    (
+        "1MooN32fuBBgApc8ujknKJw5sef3BVwPGgz3pto1BAh",
+        "MU",
+        "new moon",
+    ),
+    (
+        "WooFif76YGRNjk1pA8wCsN67aQsD9f9iLsz4NcJ1AVb",
+        "wf",
+        "woofie",
+    ),
+    (
+        "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj",
+        "lp",
+        "Launchpad Ray",
+    ),
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Program {
+    pub id: String,
+    pub code: String,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    #[serde(rename = "program", alias = "programs")]
+    programs: Vec<Program>,
+}
+
+/// Set of DEX programs whose transactions we subscribe to.
+#[derive(Debug, Clone)]
+pub struct ProgramRegistry {
+    programs: Vec<Program>,
+    // Декодированный program id -> индекс в programs
+    by_key: HashMap<[u8; 32], usize>,
+}
+
+impl ProgramRegistry {
+    pub fn new(programs: Vec<Program>) -> Result<Self> {
+        let mut registry = Self {
+            programs,
+            by_key: HashMap::new(),
+        };
+        registry.validate()?;
+        for (index, program) in registry.programs.iter().enumerate() {
+            let key = decode_pubkey(&program.id)?;
+            registry.by_key.insert(key, index);
+        }
+        Ok(registry)
+    }
+
+    pub fn builtin() -> Self {
+        let programs = BUILTIN_PROGRAMS
+            .iter()
+            .map(|(id, code, name)| Program {
+                id: id.to_string(),
+                code: code.to_string(),
+                name: name.to_string(),
+                enabled: true,
+            })
+            .collect();
+        Self::new(programs).expect("built-in program registry is valid")
+    }
+
+    /// Loads a registry from a `.toml` or `.json` file with a `program` list.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read program registry {}", path.display()))?;
+        let file: RegistryFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            Some("toml") => toml::from_str(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            _ => bail!(
+                "unsupported program registry format {} (expected .toml or .json)",
+                path.display()
+            ),
+        };
+        Self::new(file.programs)
+    }
+
+    /// Checks that every id is a base58 32-byte pubkey and that ids and codes are unique.
+    fn validate(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        let mut codes = HashSet::new();
+        for program in &self.programs {
+            decode_pubkey(&program.id)
+                .with_context(|| format!("program {} has invalid id", program.name))?;
+            if !ids.insert(program.id.as_str()) {
+                bail!("duplicate program id {}", program.id);
+            }
+            if !codes.insert(program.code.as_str()) {
+                bail!("duplicate program code {}", program.code);
+            }
+        }
+        Ok(())
+    }
+
+    /// Registry with `programs` appended, so the indices of the existing ones stay
+    /// the same; fails if the combined list does not validate.
+    pub fn with_added(&self, programs: Vec<Program>) -> Result<Self> {
+        let mut all = self.programs.clone();
+        all.extend(programs);
+        Self::new(all)
+    }
+
+    /// Enables or disables a program selected by id, code or name.
+    pub fn set_enabled(&mut self, key: &str, enabled: bool) -> Result<()> {
+        let program = self
+            .programs
+            .iter_mut()
+            .find(|p| p.id == key || p.code == key || p.name == key)
+            .with_context(|| format!("unknown program {key}"))?;
+        program.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Program> {
+        self.programs.iter().filter(|p| p.enabled)
+    }
+
+    pub fn enabled_ids(&self) -> Vec<String> {
+        self.enabled().map(|p| p.id.clone()).collect()
+    }
+
+    pub fn get(&self, index: usize) -> &Program {
+        &self.programs[index]
+    }
+
+    /// Indices of the enabled programs among `account_keys`, each reported once.
+    pub fn match_accounts<'a>(
+        &self,
+        account_keys: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Vec<usize> {
+        let mut matched = Vec::new();
+        for key in account_keys {
+            let Ok(key) = <[u8; 32]>::try_from(key) else {
+                continue;
+            };
+            if let Some(&index) = self.by_key.get(&key)
+                && self.programs[index].enabled
+                && !matched.contains(&index)
+            {
+                matched.push(index);
+            }
+        }
+        matched
+    }
+}
+
+pub fn decode_pubkey(id: &str) -> Result<[u8; 32]> {
+    let bytes = bs58::decode(id)
+        .into_vec()
+        .with_context(|| format!("{id} is not valid base58"))?;
+    let len = bytes.len();
+    <[u8; 32]>::try_from(bytes)
+        .map_err(|_| anyhow::anyhow!("{id} decodes to {len} bytes, expected 32"))
 }
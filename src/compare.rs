@@ -0,0 +1,146 @@
+use crate::metrics::Metrics;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+// Сколько слотов назад от самого свежего храним данные о прибытии
+const COMPARISON_SLOT_WINDOW: u64 = 512;
+
+/// Most endpoints one comparator can track; each takes a bit of a `u64` mask.
+pub const MAX_COMPARED_ENDPOINTS: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Arrival {
+    // Время получения первым эндпоинтом, микросекунды
+    first_ts: u64,
+    // Битовая маска эндпоинтов, которые уже доставили событие
+    seen_by: u64,
+}
+
+enum ArrivalOutcome {
+    First,
+    Behind(u64),
+    Repeat,
+}
+
+#[derive(Debug, Default)]
+struct SlotArrivals {
+    statuses: HashMap<SlotStatus, Arrival>,
+    signatures: HashMap<Vec<u8>, Arrival>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    endpoints: Vec<String>,
+    slots: BTreeMap<u64, SlotArrivals>,
+}
+
+/// Tracks which endpoint delivered each slot status and each transaction
+/// signature first, and how far behind the other endpoints were.
+#[derive(Debug, Default)]
+pub struct EndpointComparator {
+    inner: Mutex<Inner>,
+}
+
+impl EndpointComparator {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        let comparator = Self::default();
+        for name in endpoints {
+            comparator.add_endpoint(name);
+        }
+        comparator
+    }
+
+    /// Registers another endpoint and returns its index.
+    pub fn add_endpoint(&self, name: String) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        assert!(
+            inner.endpoints.len() < MAX_COMPARED_ENDPOINTS,
+            "at most {} endpoints can be compared",
+            MAX_COMPARED_ENDPOINTS
+        );
+        inner.endpoints.push(name);
+        inner.endpoints.len() - 1
+    }
+
+    /// `received_at` is the receive time in microseconds (see
+    /// `tracker::monotonic_time_us`); lags are measured in the same unit.
+    pub fn record_slot_status(
+        &self,
+        endpoint: usize,
+        slot: u64,
+        status: SlotStatus,
+        received_at: u64,
+        metrics: &Metrics,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let Inner { endpoints, slots } = &mut *inner;
+        let arrivals = slots.entry(slot).or_default();
+        let name = &endpoints[endpoint];
+        match record_arrival(arrivals.statuses.entry(status), endpoint, received_at) {
+            ArrivalOutcome::First => metrics.record_first_slot_status(name, status),
+            ArrivalOutcome::Behind(lag_us) => metrics.record_slot_status_lag(name, status, lag_us),
+            ArrivalOutcome::Repeat => {}
+        }
+        prune(slots);
+    }
+
+    pub fn record_transaction(
+        &self,
+        endpoint: usize,
+        slot: u64,
+        signature: &[u8],
+        received_at: u64,
+        metrics: &Metrics,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let Inner { endpoints, slots } = &mut *inner;
+        let arrivals = slots.entry(slot).or_default();
+        let name = &endpoints[endpoint];
+        match record_arrival(
+            arrivals.signatures.entry(signature.to_vec()),
+            endpoint,
+            received_at,
+        ) {
+            ArrivalOutcome::First => metrics.record_first_transaction(name),
+            ArrivalOutcome::Behind(lag_us) => metrics.record_transaction_lag(name, lag_us),
+            ArrivalOutcome::Repeat => {}
+        }
+        prune(slots);
+    }
+}
+
+fn record_arrival<K>(entry: Entry<'_, K, Arrival>, endpoint: usize, now: u64) -> ArrivalOutcome {
+    let bit = 1u64 << endpoint;
+    match entry {
+        Entry::Vacant(entry) => {
+            entry.insert(Arrival {
+                first_ts: now,
+                seen_by: bit,
+            });
+            ArrivalOutcome::First
+        }
+        Entry::Occupied(mut entry) => {
+            let arrival = entry.get_mut();
+            if arrival.seen_by & bit != 0 {
+                return ArrivalOutcome::Repeat;
+            }
+            arrival.seen_by |= bit;
+            ArrivalOutcome::Behind(now.saturating_sub(arrival.first_ts))
+        }
+    }
+}
+
+fn prune(slots: &mut BTreeMap<u64, SlotArrivals>) {
+    let Some(&highest) = slots.keys().next_back() else {
+        return;
+    };
+    let cutoff = highest.saturating_sub(COMPARISON_SLOT_WINDOW);
+    while let Some(entry) = slots.first_entry() {
+        if *entry.key() >= cutoff {
+            break;
+        }
+        entry.remove();
+    }
+}
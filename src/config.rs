@@ -0,0 +1,223 @@
+use crate::accounts::decode_pubkey;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use yellowstone_grpc_proto::geyser::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry, SubscribeRequestFilterSlots,
+    SubscribeRequestFilterTransactions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentLevel {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentLevel::Processed,
+            Commitment::Confirmed => CommitmentLevel::Confirmed,
+            Commitment::Finalized => CommitmentLevel::Finalized,
+        }
+    }
+}
+
+/// Optional update kinds subscribed on top of slots and transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum UpdateKind {
+    Accounts,
+    BlocksMeta,
+    Entries,
+    TransactionsStatus,
+}
+
+/// Transaction filter; also used for `transactions_status`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransactionsConfig {
+    pub vote: bool,
+    pub failed: bool,
+    /// Added to the ids of the enabled registry programs.
+    pub account_include: Vec<String>,
+    pub account_exclude: Vec<String>,
+    pub account_required: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlotsConfig {
+    pub filter_by_commitment: bool,
+    pub interslot_updates: bool,
+}
+
+impl Default for SlotsConfig {
+    fn default() -> Self {
+        Self {
+            filter_by_commitment: false,
+            interslot_updates: true,
+        }
+    }
+}
+
+/// Account filter, used only when `accounts` is subscribed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountsConfig {
+    pub account: Vec<String>,
+    pub owner: Vec<String>,
+}
+
+/// Everything that goes into the `SubscribeRequest`, loaded from a TOML file
+/// and then adjusted by CLI flags. The defaults match the request the
+/// monitor has always sent.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscriptionConfig {
+    pub commitment: Commitment,
+    pub subscribe: Vec<UpdateKind>,
+    pub transactions: TransactionsConfig,
+    pub slots: SlotsConfig,
+    pub accounts: AccountsConfig,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            commitment: Commitment::Confirmed,
+            subscribe: Vec::new(),
+            transactions: TransactionsConfig::default(),
+            slots: SlotsConfig::default(),
+            accounts: AccountsConfig::default(),
+        }
+    }
+}
+
+impl SubscriptionConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read subscription config {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn subscribes(&self, kind: UpdateKind) -> bool {
+        self.subscribe.contains(&kind)
+    }
+
+    /// Checks account lists before they reach the server, which would only
+    /// report the first bad one.
+    pub fn validate(&self) -> Result<()> {
+        let lists = [
+            (
+                "transactions.account_include",
+                &self.transactions.account_include,
+            ),
+            (
+                "transactions.account_exclude",
+                &self.transactions.account_exclude,
+            ),
+            (
+                "transactions.account_required",
+                &self.transactions.account_required,
+            ),
+            ("accounts.account", &self.accounts.account),
+            ("accounts.owner", &self.accounts.owner),
+        ];
+        for (field, keys) in lists {
+            for key in keys {
+                decode_pubkey(key).with_context(|| format!("invalid pubkey in {}", field))?;
+            }
+        }
+
+        // Без фильтра сервер пришлет все аккаунты сети
+        if self.subscribes(UpdateKind::Accounts)
+            && self.accounts.account.is_empty()
+            && self.accounts.owner.is_empty()
+        {
+            bail!("accounts subscription needs at least one account or owner");
+        }
+        Ok(())
+    }
+
+    /// Builds the request; `program_ids` are the enabled registry programs.
+    pub fn request(&self, program_ids: &[String], from_slot: Option<u64>) -> SubscribeRequest {
+        let mut account_include = program_ids.to_vec();
+        for key in &self.transactions.account_include {
+            if !account_include.contains(key) {
+                account_include.push(key.clone());
+            }
+        }
+        let transaction_filter = SubscribeRequestFilterTransactions {
+            vote: Some(self.transactions.vote),
+            failed: Some(self.transactions.failed),
+            signature: None,
+            account_include,
+            account_exclude: self.transactions.account_exclude.clone(),
+            account_required: self.transactions.account_required.clone(),
+        };
+
+        let mut transactions_status = HashMap::new();
+        if self.subscribes(UpdateKind::TransactionsStatus) {
+            transactions_status.insert("amm_transactions".to_string(), transaction_filter.clone());
+        }
+
+        let mut transactions = HashMap::new();
+        transactions.insert("amm_transactions".to_string(), transaction_filter);
+
+        let mut accounts = HashMap::new();
+        if self.subscribes(UpdateKind::Accounts) {
+            accounts.insert(
+                "accounts".to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: self.accounts.account.clone(),
+                    owner: self.accounts.owner.clone(),
+                    filters: vec![],
+                    nonempty_txn_signature: None,
+                },
+            );
+        }
+
+        let mut slots = HashMap::new();
+        slots.insert(
+            "client".to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(self.slots.filter_by_commitment),
+                interslot_updates: Some(self.slots.interslot_updates),
+            },
+        );
+
+        let mut blocks_meta = HashMap::new();
+        if self.subscribes(UpdateKind::BlocksMeta) {
+            blocks_meta.insert(
+                "blocks_meta".to_string(),
+                SubscribeRequestFilterBlocksMeta {},
+            );
+        }
+
+        let mut entry = HashMap::new();
+        if self.subscribes(UpdateKind::Entries) {
+            entry.insert("entries".to_string(), SubscribeRequestFilterEntry {});
+        }
+
+        SubscribeRequest {
+            slots,
+            accounts,
+            transactions,
+            transactions_status,
+            blocks: HashMap::new(),
+            blocks_meta,
+            entry,
+            commitment: Some(CommitmentLevel::from(self.commitment) as i32),
+            accounts_data_slice: vec![],
+            ping: None,
+            from_slot,
+        }
+    }
+}
@@ -0,0 +1,533 @@
+use crate::metrics::status_label;
+use crate::summary::SlotSummary;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{mpsc, oneshot};
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+// Сколько сводок может ждать записи, прежде чем начнем их отбрасывать
+const EXPORT_QUEUE_SIZE: usize = 4096;
+
+// Статусы в порядке прохождения слота; для каждого есть колонки времени и транзакций
+const EXPORT_STATUSES: &[SlotStatus] = &[
+    SlotStatus::SlotFirstShredReceived,
+    SlotStatus::SlotCreatedBank,
+    SlotStatus::SlotCompleted,
+    SlotStatus::SlotProcessed,
+    SlotStatus::SlotConfirmed,
+    SlotStatus::SlotFinalized,
+    SlotStatus::SlotDead,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    /// Requires the `parquet` cargo feature.
+    Parquet,
+}
+
+/// How often a new export file is started, by the slot's creation time (UTC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Rotation {
+    Hourly,
+    Daily,
+}
+
+impl Rotation {
+    /// `2026-10-18T13` for hourly files, `2026-10-18` for daily ones.
+    fn period(&self, unix_us: u64) -> String {
+        let secs = unix_us / 1_000_000;
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        match self {
+            Rotation::Hourly => {
+                format!("{year:04}-{month:02}-{day:02}T{:02}", secs % 86_400 / 3600)
+            }
+            Rotation::Daily => format!("{year:04}-{month:02}-{day:02}"),
+        }
+    }
+}
+
+// Дата по числу дней от 1970-01-01 (алгоритм Howard Hinnant, civil_from_days)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    UInt64,
+    Int64,
+    Utf8,
+}
+
+/// One value of an exported row; `None` is written as an empty CSV field or a
+/// Parquet null.
+#[derive(Debug, Clone)]
+enum Cell {
+    UInt64(Option<u64>),
+    Int64(Option<i64>),
+    Utf8(Option<String>),
+}
+
+/// Column names and types of the export schema, in file order.
+fn columns() -> Vec<(String, ColumnType)> {
+    let mut columns: Vec<(String, ColumnType)> = [
+        ("endpoint", ColumnType::Utf8),
+        ("slot", ColumnType::UInt64),
+        ("outcome", ColumnType::Utf8),
+        ("eviction_reason", ColumnType::Utf8),
+        ("creator", ColumnType::Utf8),
+        ("created_at_us", ColumnType::UInt64),
+        ("first_tx_at_us", ColumnType::UInt64),
+        ("last_tx_at_us", ColumnType::UInt64),
+        ("duration_us", ColumnType::UInt64),
+        ("total_txs", ColumnType::UInt64),
+        ("duplicate_txs", ColumnType::UInt64),
+        ("tx_no_status_yet", ColumnType::UInt64),
+    ]
+    .into_iter()
+    .map(|(name, ty)| (name.to_string(), ty))
+    .collect();
+    for &status in EXPORT_STATUSES {
+        columns.push((
+            format!("{}_at_us", status_label(status)),
+            ColumnType::UInt64,
+        ));
+    }
+    for &status in EXPORT_STATUSES {
+        columns.push((format!("tx_{}", status_label(status)), ColumnType::UInt64));
+    }
+    // Разбивка по программам: "R:2 W:1", как в текстовом выводе
+    columns.push(("tx_by_program".to_string(), ColumnType::Utf8));
+    // Порядок прихода относительно индексов транзакций в блоке
+    for (name, ty) in [
+        ("min_tx_index", ColumnType::UInt64),
+        ("max_tx_index", ColumnType::UInt64),
+        ("out_of_order_txs", ColumnType::UInt64),
+        ("missing_tx_indices", ColumnType::UInt64),
+        ("first_to_last_index_us", ColumnType::Int64),
+        // Сверка с BlockMeta, если на нее подписаны
+        ("block_height", ColumnType::UInt64),
+        ("block_time", ColumnType::Int64),
+        ("blockhash", ColumnType::Utf8),
+        ("executed_txs", ColumnType::UInt64),
+        ("unobserved_txs", ColumnType::UInt64),
+    ] {
+        columns.push((name.to_string(), ty));
+    }
+    columns
+}
+
+fn row(summary: &SlotSummary) -> Vec<Cell> {
+    let tx_count = |status: Option<SlotStatus>| {
+        summary
+            .tx_by_status
+            .iter()
+            .find(|(s, _)| *s == status)
+            .map_or(0, |(_, count)| *count)
+    };
+    let eviction_reason = summary
+        .eviction_reason
+        .map(|reason| reason.as_str().to_string());
+
+    let mut row = vec![
+        Cell::Utf8(Some(summary.endpoint.clone())),
+        Cell::UInt64(Some(summary.slot)),
+        Cell::Utf8(Some(summary.outcome.label().to_string())),
+        Cell::Utf8(eviction_reason),
+        Cell::Utf8(Some(summary.creator.clone())),
+        Cell::UInt64(Some(summary.created_at_us)),
+        Cell::UInt64(summary.first_tx_at_us),
+        Cell::UInt64(summary.last_tx_at_us),
+        Cell::UInt64(Some(summary.duration_us)),
+        Cell::UInt64(Some(summary.total_txs)),
+        Cell::UInt64(Some(summary.duplicate_txs)),
+        Cell::UInt64(Some(tx_count(None))),
+    ];
+    for &status in EXPORT_STATUSES {
+        row.push(Cell::UInt64(summary.status_ts(status)));
+    }
+    for &status in EXPORT_STATUSES {
+        row.push(Cell::UInt64(Some(tx_count(Some(status)))));
+    }
+    let programs = summary
+        .tx_by_program
+        .iter()
+        .map(|program| format!("{}:{}", program.code, program.tx_count))
+        .collect::<Vec<_>>();
+    row.push(Cell::Utf8(Some(programs.join(" "))));
+    let tx_index = summary.tx_index.as_ref();
+    row.extend([
+        Cell::UInt64(tx_index.map(|index| index.min_index)),
+        Cell::UInt64(tx_index.map(|index| index.max_index)),
+        Cell::UInt64(tx_index.map(|index| index.out_of_order_txs)),
+        Cell::UInt64(tx_index.map(|index| index.missing_indices)),
+        Cell::Int64(tx_index.map(|index| index.first_to_last_index_us)),
+    ]);
+    let block_meta = summary.block_meta.as_ref();
+    row.extend([
+        Cell::UInt64(block_meta.and_then(|meta| meta.block_height)),
+        Cell::Int64(block_meta.and_then(|meta| meta.block_time)),
+        Cell::Utf8(block_meta.map(|meta| meta.blockhash.clone())),
+        Cell::UInt64(block_meta.map(|meta| meta.executed_transaction_count)),
+        Cell::UInt64(block_meta.map(|meta| meta.unobserved_txs)),
+    ]);
+    row
+}
+
+enum ExportCommand {
+    Write(Box<SlotSummary>),
+    /// Close the current file and stop; the sender is told once it is done.
+    Close(oneshot::Sender<()>),
+}
+
+/// Handle for queueing slot summaries to the background export writer.
+#[derive(Debug, Clone)]
+pub struct SlotExporter {
+    tx: mpsc::Sender<ExportCommand>,
+    // Сводки, которые так и не попали в файл
+    dropped: Arc<AtomicU64>,
+}
+
+impl SlotExporter {
+    /// Starts a writer that appends one row per slot summary to
+    /// `slots-<period>` files in `dir`, starting a new file every hour or day.
+    pub fn start(dir: PathBuf, format: ExportFormat, rotation: Rotation) -> Result<Self> {
+        if format == ExportFormat::Parquet && !cfg!(feature = "parquet") {
+            bail!("Parquet export needs a build with the `parquet` feature");
+        }
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create export directory {}", dir.display()))?;
+        let (tx, mut rx) = mpsc::channel::<ExportCommand>(EXPORT_QUEUE_SIZE);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer_dropped = dropped.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut current: Option<(String, Box<dyn PeriodFile>)> = None;
+            while let Some(command) = rx.blocking_recv() {
+                let summary = match command {
+                    ExportCommand::Write(summary) => summary,
+                    ExportCommand::Close(done) => {
+                        close_period_file(current.take());
+                        let _ = done.send(());
+                        return;
+                    }
+                };
+                let period = rotation.period(summary.created_at_us);
+                let result = (|| {
+                    // Файлы переключаем только вперед: запоздавшие сводки прошлого
+                    // периода (вытеснение, медленный эндпоинт) пишем в текущий файл
+                    if current.as_ref().is_none_or(|(p, _)| *p < period) {
+                        if let Some((_, file)) = current.take() {
+                            file.close()?;
+                        }
+                        current = Some((period.clone(), open_period_file(&dir, format, &period)?));
+                    }
+                    let (_, file) = current.as_mut().unwrap();
+                    file.write(row(&summary))?;
+                    // Сбрасываем буфер, когда очередь опустела
+                    if rx.is_empty() {
+                        file.flush()?;
+                    }
+                    Ok::<_, anyhow::Error>(())
+                })();
+                if let Err(e) = result {
+                    // Файл бросаем, следующая сводка попробует открыть его заново
+                    close_period_file(current.take());
+                    let dropped = writer_dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    eprintln!(
+                        "Failed to export slot {}: {:#} ({} summaries dropped so far)",
+                        summary.slot, e, dropped
+                    );
+                }
+            }
+            close_period_file(current);
+        });
+
+        Ok(Self { tx, dropped })
+    }
+
+    pub fn export(&self, summary: &SlotSummary) {
+        let reason = match self
+            .tx
+            .try_send(ExportCommand::Write(Box::new(summary.clone())))
+        {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(_)) => "queue is full",
+            Err(mpsc::error::TrySendError::Closed(_)) => "writer has stopped",
+        };
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        eprintln!(
+            "Export {}, dropping slot {} ({} summaries dropped so far)",
+            reason, summary.slot, dropped
+        );
+    }
+
+    /// Closes the current file, e.g. on shutdown, so a Parquet file gets its
+    /// footer. Summaries exported afterwards are dropped.
+    pub async fn close(&self) {
+        let (done, closed) = oneshot::channel();
+        if self.tx.send(ExportCommand::Close(done)).await.is_ok() {
+            let _ = closed.await;
+        }
+    }
+}
+
+fn close_period_file(current: Option<(String, Box<dyn PeriodFile>)>) {
+    if let Some((_, file)) = current
+        && let Err(e) = file.close()
+    {
+        eprintln!("Failed to close export file: {:#}", e);
+    }
+}
+
+/// The open file of one rotation period.
+trait PeriodFile: Send {
+    fn write(&mut self, row: Vec<Cell>) -> Result<()>;
+    fn flush(&mut self) -> Result<()>;
+    fn close(self: Box<Self>) -> Result<()>;
+}
+
+fn open_period_file(dir: &Path, format: ExportFormat, period: &str) -> Result<Box<dyn PeriodFile>> {
+    match format {
+        ExportFormat::Csv => Ok(Box::new(CsvFile::open(dir, period)?)),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => Ok(Box::new(parquet_file::ParquetFile::create(dir, period)?)),
+        #[cfg(not(feature = "parquet"))]
+        ExportFormat::Parquet => unreachable!("checked in SlotExporter::start"),
+    }
+}
+
+/// CSV file of a period; reopening after a restart appends without a second
+/// header. A file written with other columns (an older version) is left
+/// alone and the rows go to `slots-<period>-<n>.csv` instead.
+struct CsvFile {
+    writer: csv::Writer<File>,
+}
+
+impl CsvFile {
+    fn open(dir: &Path, period: &str) -> Result<Self> {
+        let header = columns()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        let mut suffix = 0;
+        loop {
+            let name = match suffix {
+                0 => format!("slots-{period}.csv"),
+                n => format!("slots-{period}-{n}.csv"),
+            };
+            let path = dir.join(name);
+            if existing_header(&path)?.is_none_or(|existing| existing == header) {
+                return Self::append(&path);
+            }
+            suffix += 1;
+        }
+    }
+
+    fn append(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open export file {}", path.display()))?;
+        let is_new = file.metadata()?.len() == 0;
+        let mut writer = csv::Writer::from_writer(file);
+        if is_new {
+            writer.write_record(columns().iter().map(|(name, _)| name))?;
+        }
+        eprintln!("Exporting slot summaries to {}", path.display());
+        Ok(Self { writer })
+    }
+}
+
+/// Column names of an existing non-empty CSV file.
+fn existing_header(path: &Path) -> Result<Option<Vec<String>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed to open export file {}", path.display()));
+        }
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    let mut reader = csv::Reader::from_reader(file);
+    let header = reader
+        .headers()
+        .with_context(|| format!("failed to read header of {}", path.display()))?;
+    Ok(Some(header.iter().map(str::to_string).collect()))
+}
+
+impl PeriodFile for CsvFile {
+    fn write(&mut self, row: Vec<Cell>) -> Result<()> {
+        let fields = row.into_iter().map(|cell| match cell {
+            Cell::UInt64(value) => value.map(|v| v.to_string()).unwrap_or_default(),
+            Cell::Int64(value) => value.map(|v| v.to_string()).unwrap_or_default(),
+            Cell::Utf8(value) => value.unwrap_or_default(),
+        });
+        self.writer.write_record(fields)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn close(mut self: Box<Self>) -> Result<()> {
+        self.flush()
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_file {
+    use super::{Cell, ColumnType, PeriodFile, columns};
+    use anyhow::{Context, Result};
+    use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    // Строк в одной группе строк Parquet
+    const ROWS_PER_BATCH: usize = 1024;
+    // Дольше строки в памяти не держим, даже если батч не набрался
+    const MAX_BATCH_AGE: Duration = Duration::from_secs(60);
+
+    /// Parquet file of a period. It is written under a `.partial` name and
+    /// renamed once the period ends, so readers never see a file without footer.
+    pub struct ParquetFile {
+        schema: SchemaRef,
+        writer: ArrowWriter<File>,
+        rows: Vec<Vec<Cell>>,
+        // Когда в батч попала первая строка
+        batch_started: Option<Instant>,
+        partial_path: PathBuf,
+        path: PathBuf,
+    }
+
+    impl ParquetFile {
+        pub fn create(dir: &Path, period: &str) -> Result<Self> {
+            // Parquet не дописать, поэтому после перезапуска в том же периоде начинаем новый файл
+            let opened_at = crate::tracker::unix_time_us() / 1000;
+            let path = dir.join(format!("slots-{period}-{opened_at}.parquet"));
+            let partial_path = path.with_extension("parquet.partial");
+
+            let fields = columns()
+                .into_iter()
+                .map(|(name, ty)| {
+                    let data_type = match ty {
+                        ColumnType::UInt64 => DataType::UInt64,
+                        ColumnType::Int64 => DataType::Int64,
+                        ColumnType::Utf8 => DataType::Utf8,
+                    };
+                    Field::new(name, data_type, true)
+                })
+                .collect::<Vec<_>>();
+            let schema = Arc::new(Schema::new(fields));
+
+            let file = File::create(&partial_path).with_context(|| {
+                format!("failed to create export file {}", partial_path.display())
+            })?;
+            let writer = ArrowWriter::try_new(file, schema.clone(), None)?;
+            eprintln!("Exporting slot summaries to {}", path.display());
+            Ok(Self {
+                schema,
+                writer,
+                rows: Vec::new(),
+                batch_started: None,
+                partial_path,
+                path,
+            })
+        }
+
+        fn write_batch(&mut self) -> Result<()> {
+            if self.rows.is_empty() {
+                return Ok(());
+            }
+            let rows = std::mem::take(&mut self.rows);
+            self.batch_started = None;
+            let arrays = (0..self.schema.fields().len())
+                .map(|column| -> ArrayRef {
+                    match rows[0][column] {
+                        Cell::UInt64(_) => Arc::new(
+                            rows.iter()
+                                .map(|row| match &row[column] {
+                                    Cell::UInt64(value) => *value,
+                                    _ => None,
+                                })
+                                .collect::<UInt64Array>(),
+                        ),
+                        Cell::Int64(_) => Arc::new(
+                            rows.iter()
+                                .map(|row| match &row[column] {
+                                    Cell::Int64(value) => *value,
+                                    _ => None,
+                                })
+                                .collect::<Int64Array>(),
+                        ),
+                        Cell::Utf8(_) => Arc::new(
+                            rows.iter()
+                                .map(|row| match &row[column] {
+                                    Cell::Utf8(value) => value.clone(),
+                                    _ => None,
+                                })
+                                .collect::<StringArray>(),
+                        ),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+            self.writer.write(&batch)?;
+            Ok(())
+        }
+    }
+
+    impl PeriodFile for ParquetFile {
+        fn write(&mut self, row: Vec<Cell>) -> Result<()> {
+            self.batch_started.get_or_insert_with(Instant::now);
+            self.rows.push(row);
+            if self.rows.len() >= ROWS_PER_BATCH {
+                self.write_batch()?;
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            // Строки копим до целого батча, иначе группы строк выйдут крошечными,
+            // но не дольше MAX_BATCH_AGE
+            if self
+                .batch_started
+                .is_some_and(|started| started.elapsed() >= MAX_BATCH_AGE)
+            {
+                self.write_batch()?;
+                self.writer.flush()?;
+            }
+            Ok(())
+        }
+
+        fn close(mut self: Box<Self>) -> Result<()> {
+            self.write_batch()?;
+            self.writer.close()?;
+            std::fs::rename(&self.partial_path, &self.path)?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Slot timing monitor for Yellowstone gRPC (Geyser) streams.
+//!
+//! The engine is [`tracker::SlotTrackerSet`]: feed it `SubscribeUpdate`s and
+//! it emits a [`summary::SlotSummary`] for every slot that finalizes, dies or
+//! is evicted. [`stream`] keeps endpoints subscribed and drives the engine,
+//! handing every summary to a [`sink::SlotSink`] such as the Prometheus
+//! [`metrics`], and [`accounts`] holds the registry of programs whose
+//! transactions are tracked.
+
+pub mod accounts;
+pub mod compare;
+pub mod config;
+pub mod export;
+pub mod metrics;
+pub mod output;
+pub mod recording;
+pub mod server;
+pub mod sink;
+pub mod status;
+pub mod stream;
+pub mod subscription;
+pub mod summary;
+pub mod tls;
+pub mod tracker;
@@ -1,129 +1,486 @@
-mod accounts;
-mod metrics;
-mod server;
-
-use crate::accounts::get_accounts;
-use crate::metrics::Metrics;
-use crate::server::start_metrics_server;
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio_stream::StreamExt;
-use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
-use yellowstone_grpc_proto::geyser::{
-    CommitmentLevel, SlotStatus, SubscribeRequest, SubscribeRequestFilterSlots,
-    SubscribeRequestFilterTransactions, subscribe_update,
+use grpc_connect_test::accounts::ProgramRegistry;
+use grpc_connect_test::compare::{EndpointComparator, MAX_COMPARED_ENDPOINTS};
+use grpc_connect_test::config::{Commitment, SubscriptionConfig, UpdateKind};
+use grpc_connect_test::export::{ExportFormat, Rotation, SlotExporter};
+use grpc_connect_test::metrics::Metrics;
+use grpc_connect_test::output::{OutputFormat, SummaryOutput};
+use grpc_connect_test::recording::{Recorder, replay};
+use grpc_connect_test::server::{start_admin_server, start_metrics_server};
+use grpc_connect_test::sink::{SinkSet, SlotSink};
+use grpc_connect_test::status::StreamStatus;
+use grpc_connect_test::stream::{
+    ConnectionConfig, EndpointContext, KeepaliveConfig, QueueConfig, ReconnectConfig, run_endpoint,
 };
+use grpc_connect_test::subscription::Subscription;
+use grpc_connect_test::tls::TlsOptions;
+use grpc_connect_test::tracker::EvictionPolicy;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
 
-#[derive(Debug, Clone)]
-struct SlotTracker {
-    slot: u64,
-    creator: String,
-    create_ts: u64,
-    first_tx_ts: Option<u64>,
-    last_tx_ts: Option<u64>,
-    current_status: Option<SlotStatus>,
-    // Счетчики транзакций по статусам (используем SlotStatus как ключ)
-    tx_counts: HashMap<SlotStatus, u64>,
-    // Отдельный счетчик транзакций для трекеров, созданных транзакциями
-    tx_initiated_count: u64,
-}
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(
+        short,
+        long = "endpoint",
+        default_value = "http://127.0.0.1:10000",
+        help = "gRPC endpoint as URL or NAME=URL; repeat to compare several endpoints"
+    )]
+    endpoints: Vec<String>,
+
+    #[arg(
+        short,
+        long = "x-token",
+        help = "x-token for the endpoint at the same position; pass \"\" to skip one"
+    )]
+    x_tokens: Vec<String>,
 
-impl SlotTracker {
-    fn new(slot: u64, creator: String) -> Self {
-        // println!("Create slot tracker");
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-
-        Self {
-            slot,
-            creator,
-            create_ts: now,
-            first_tx_ts: None,
-            last_tx_ts: None,
-            current_status: None,
-            tx_counts: HashMap::new(),
-            tx_initiated_count: 0,
-        }
-    }
+    #[arg(long, help = "Skip TLS certificate verification (insecure)")]
+    insecure: bool,
 
-    fn apply_transaction(&mut self) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+    #[arg(
+        long,
+        conflicts_with = "insecure",
+        help = "PEM CA bundle to trust in addition to the system roots"
+    )]
+    ca_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "client_key",
+        help = "PEM client certificate for mutual TLS"
+    )]
+    client_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "client_cert",
+        help = "PEM private key of --client-cert"
+    )]
+    client_key: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Server name for SNI and certificate checks instead of the endpoint host"
+    )]
+    tls_domain: Option<String>,
+
+    #[arg(
+        long,
+        help = "Program registry file (.toml or .json); the built-in DEX list is used if omitted"
+    )]
+    programs: Option<PathBuf>,
+
+    #[arg(long, help = "Enable a program by id, code or name; repeatable")]
+    enable_program: Vec<String>,
+
+    #[arg(long, help = "Disable a program by id, code or name; repeatable")]
+    disable_program: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Record every received update to rotating files in this directory"
+    )]
+    record: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value = "256",
+        help = "Start a new recording file after this many megabytes"
+    )]
+    record_max_file_mb: u64,
+
+    #[arg(
+        long,
+        conflicts_with = "record",
+        help = "Replay a recording file or directory instead of connecting"
+    )]
+    replay: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "replay",
+        help = "Keep the original timing between updates when replaying"
+    )]
+    replay_realtime: bool,
+
+    #[arg(
+        long,
+        default_value = "120",
+        help = "Evict slot trackers older than this many seconds (0 = never)"
+    )]
+    evict_max_age_secs: u64,
+
+    #[arg(
+        long,
+        default_value = "150",
+        help = "Evict slot trackers this many slots behind the highest seen slot (0 = never)"
+    )]
+    evict_max_slot_distance: u64,
 
-        // Устанавливаем время первой транзакции, если это первая
-        if self.first_tx_ts.is_none() {
-            self.first_tx_ts = Some(now);
-        }
+    #[arg(long, default_value = "9090", help = "Prometheus metrics server port")]
+    metrics_port: u16,
 
-        self.last_tx_ts = Some(now);
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with = "replay",
+        help = "Serve the unauthenticated subscription admin API on this address, e.g. 127.0.0.1:9091 (off by default)"
+    )]
+    admin_listen: Option<SocketAddr>,
+
+    #[arg(
+        long,
+        help = "Stop exporting the deprecated slot_transactions_<status> counters (use slot_transactions_total)"
+    )]
+    no_legacy_metrics: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Format of slot summaries: text lines or JSON lines"
+    )]
+    output_format: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Append slot summaries to this file instead of stdout"
+    )]
+    output_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "List the signature of every counted transaction in JSON slot summaries"
+    )]
+    summary_signatures: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Also append every slot summary as a row to rotating files in this directory"
+    )]
+    export_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ExportFormat::Csv,
+        requires = "export_dir",
+        help = "Format of exported files (parquet needs the `parquet` feature)"
+    )]
+    export_format: ExportFormat,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Rotation::Hourly,
+        help = "Start a new export file every hour or day (UTC, by slot creation time)"
+    )]
+    export_rotation: Rotation,
+
+    #[arg(
+        long = "sink",
+        value_name = "SINK",
+        value_parser = parse_sink,
+        conflicts_with_all = ["output_format", "output_file", "export_dir"],
+        help = "Where slot summaries go: text[:FILE], json[:FILE], prometheus, csv:DIR or parquet:DIR; \
+                repeatable. Without it: --output-format/--output-file, prometheus and --export-dir"
+    )]
+    sinks: Vec<SinkSpec>,
+
+    #[arg(
+        long,
+        default_value = "500",
+        help = "Initial reconnect backoff (milliseconds)"
+    )]
+    reconnect_initial_backoff_ms: u64,
+
+    #[arg(
+        long,
+        default_value = "30000",
+        help = "Maximum reconnect backoff (milliseconds)"
+    )]
+    reconnect_max_backoff_ms: u64,
+
+    #[arg(
+        long,
+        default_value = "0",
+        help = "Give up after this many consecutive failed reconnects (0 = never)"
+    )]
+    max_reconnect_attempts: u32,
+
+    #[arg(
+        long,
+        default_value = "10000",
+        help = "Ping the server over the subscribe stream this often (milliseconds, 0 = never)"
+    )]
+    ping_interval_ms: u64,
+
+    #[arg(
+        long,
+        default_value = "30000",
+        help = "Reconnect when no update arrives for this long (milliseconds, 0 = never)"
+    )]
+    stall_timeout_ms: u64,
+
+    #[arg(
+        long,
+        default_value = "16384",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "How many received updates may wait for processing per endpoint"
+    )]
+    processing_queue_size: u64,
+
+    #[arg(
+        long,
+        help = "Drop updates while the processing queue is full instead of pausing reads; dropped updates are not recorded either"
+    )]
+    drop_when_queue_full: bool,
+
+    #[arg(
+        long,
+        default_value = "10000",
+        help = "Report a stream as stalled on /ready after this long without updates (milliseconds, 0 = never)"
+    )]
+    ready_max_silence_ms: u64,
+
+    #[arg(
+        long,
+        help = "TOML file with subscription settings; the flags below override it"
+    )]
+    subscription_config: Option<PathBuf>,
+
+    #[arg(long, value_enum, help = "Commitment level of the subscription")]
+    commitment: Option<Commitment>,
+
+    #[arg(long, help = "Include vote transactions (true/false)")]
+    vote: Option<bool>,
+
+    #[arg(long, help = "Include failed transactions (true/false)")]
+    failed: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Extra account for the transaction filter's account_include; repeatable"
+    )]
+    account_include: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Account for the transaction filter's account_exclude; repeatable"
+    )]
+    account_exclude: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Account for the transaction filter's account_required; repeatable"
+    )]
+    account_required: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Only send slot updates at the subscription commitment (true/false)"
+    )]
+    slots_filter_by_commitment: Option<bool>,
+
+    #[arg(long, help = "Send intermediate slot statuses (true/false)")]
+    slots_interslot_updates: Option<bool>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Also subscribe to this update kind; repeatable"
+    )]
+    subscribe: Vec<UpdateKind>,
+
+    #[arg(
+        long,
+        help = "Account to watch when subscribed to accounts; repeatable"
+    )]
+    accounts_account: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Owner program to watch when subscribed to accounts; repeatable"
+    )]
+    accounts_owner: Vec<String>,
+}
 
-        match self.current_status {
-            None => self.tx_initiated_count += 1,
-            Some(current_status) => {
-                *self.tx_counts.entry(current_status).or_insert(0) += 1;
-            }
-        }
-    }
+/// A `--sink` value.
+#[derive(Debug, Clone)]
+enum SinkSpec {
+    /// Summary lines to stdout, or appended to a file.
+    Text(Option<PathBuf>),
+    Json(Option<PathBuf>),
+    Prometheus,
+    /// Rows in rotating files in a directory.
+    Csv(PathBuf),
+    Parquet(PathBuf),
+}
 
-    fn update_status(&mut self, new_status: SlotStatus) {
-        self.current_status = Some(new_status);
+fn parse_sink(spec: &str) -> Result<SinkSpec, String> {
+    let (kind, path) = match spec.split_once(':') {
+        Some((kind, path)) => (kind, Some(PathBuf::from(path))),
+        None => (spec, None),
+    };
+    match (kind, path) {
+        ("text", path) => Ok(SinkSpec::Text(path)),
+        ("json", path) => Ok(SinkSpec::Json(path)),
+        ("prometheus", None) => Ok(SinkSpec::Prometheus),
+        ("prometheus", Some(_)) => Err("the prometheus sink takes no path".to_string()),
+        ("csv", Some(dir)) => Ok(SinkSpec::Csv(dir)),
+        ("parquet", Some(dir)) => Ok(SinkSpec::Parquet(dir)),
+        ("csv" | "parquet", None) => Err(format!("the {kind} sink needs a directory: {kind}:DIR")),
+        _ => Err(format!(
+            "unknown sink {kind:?}, expected text, json, prometheus, csv or parquet"
+        )),
     }
+}
 
-    fn print_summary(&self, event: &str, metrics: &Metrics) {
-        let duration_ms = if let Some(last_tx_ts) = self.last_tx_ts {
-            last_tx_ts - self.first_tx_ts.unwrap()
-        } else {
-            0
-        };
+/// Sinks from `--sink`, or the ones the older output flags describe, plus the
+/// exporters among them, which have to be closed before exiting.
+fn slot_sinks(args: &Args, metrics: &Metrics) -> Result<(SinkSet, Vec<SlotExporter>)> {
+    let specs = if args.sinks.is_empty() {
+        let mut specs = vec![
+            match args.output_format {
+                OutputFormat::Text => SinkSpec::Text(args.output_file.clone()),
+                OutputFormat::Json => SinkSpec::Json(args.output_file.clone()),
+            },
+            SinkSpec::Prometheus,
+        ];
+        if let Some(dir) = &args.export_dir {
+            specs.push(match args.export_format {
+                ExportFormat::Csv => SinkSpec::Csv(dir.clone()),
+                ExportFormat::Parquet => SinkSpec::Parquet(dir.clone()),
+            });
+        }
+        specs
+    } else {
+        args.sinks.clone()
+    };
 
-        let total_txs = self.tx_counts.values().sum::<u64>() + self.tx_initiated_count;
+    let output = |path: Option<PathBuf>, format| match path {
+        Some(path) => SummaryOutput::file(&path, format),
+        None => Ok(SummaryOutput::stdout(format)),
+    };
+    let mut exporters = Vec::new();
+    let mut export = |dir, format| -> Result<SlotExporter> {
+        let exporter = SlotExporter::start(dir, format, args.export_rotation)?;
+        exporters.push(exporter.clone());
+        Ok(exporter)
+    };
+    let mut sinks = SinkSet::default();
+    for spec in specs {
+        let sink: Arc<dyn SlotSink> = match spec {
+            SinkSpec::Text(path) => Arc::new(output(path, OutputFormat::Text)?),
+            SinkSpec::Json(path) => Arc::new(output(path, OutputFormat::Json)?),
+            SinkSpec::Prometheus => Arc::new(metrics.clone()),
+            SinkSpec::Csv(dir) => Arc::new(export(dir, ExportFormat::Csv)?),
+            SinkSpec::Parquet(dir) => Arc::new(export(dir, ExportFormat::Parquet)?),
+        };
+        sinks.push(sink);
+    }
+    Ok((sinks, exporters))
+}
 
-        let mut status_counts = Vec::new();
-        if self.tx_initiated_count > 0 {
-            status_counts.push(format!("no_status_yet:{}", self.tx_initiated_count));
-        }
-        for (status, count) in &self.tx_counts {
-            if *count > 0 {
-                status_counts.push(format!("{:?}:{}", status, count));
-            }
+/// Resolves on Ctrl-C or, on Unix, SIGTERM.
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
         }
-
-        // Обновляем Prometheus метрики
-        metrics.record_slot_finalized(duration_ms, self.tx_initiated_count, &self.tx_counts);
-
-        // Выводим в логи для отладки
-        println!(
-            "{event} slot:{} creator:{} duration:{}ms total_txs:{} tx_by_status:[{}]",
-            self.slot,
-            self.creator,
-            duration_ms,
-            total_txs,
-            status_counts.join(" ")
-        );
     }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
 }
 
+async fn close_exporters(exporters: &[SlotExporter]) {
+    for exporter in exporters {
+        exporter.close().await;
+    }
+}
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long, default_value = "http://127.0.0.1:10000")]
-    endpoint: String,
+/// Parses `NAME=URL` or a bare `URL`, in which case the URL's host is used as the name.
+fn parse_endpoint(spec: &str) -> (String, String) {
+    if let Some((name, url)) = spec.split_once('=')
+        && !name.contains(':')
+        && !name.contains('/')
+    {
+        return (name.to_string(), url.to_string());
+    }
 
-    #[arg(short, long)]
-    x_token: Option<String>,
+    let host = spec
+        .split_once("://")
+        .map_or(spec, |(_, rest)| rest)
+        .split(['/', '?'])
+        .next()
+        .unwrap_or(spec);
+    (host.to_string(), spec.to_string())
+}
 
-    #[arg(long, help = "Skip TLS certificate verification (insecure)")]
-    insecure: bool,
+/// Subscription settings from `--subscription-config`, with CLI flags applied on top.
+fn subscription_config(args: &Args) -> Result<SubscriptionConfig> {
+    let mut config = match &args.subscription_config {
+        Some(path) => SubscriptionConfig::load(path)?,
+        None => SubscriptionConfig::default(),
+    };
 
-    #[arg(long, default_value = "9090", help = "Prometheus metrics server port")]
-    metrics_port: u16,
+    if let Some(commitment) = args.commitment {
+        config.commitment = commitment;
+    }
+    if let Some(vote) = args.vote {
+        config.transactions.vote = vote;
+    }
+    if let Some(failed) = args.failed {
+        config.transactions.failed = failed;
+    }
+    config
+        .transactions
+        .account_include
+        .extend(args.account_include.iter().cloned());
+    config
+        .transactions
+        .account_exclude
+        .extend(args.account_exclude.iter().cloned());
+    config
+        .transactions
+        .account_required
+        .extend(args.account_required.iter().cloned());
+    if let Some(filter_by_commitment) = args.slots_filter_by_commitment {
+        config.slots.filter_by_commitment = filter_by_commitment;
+    }
+    if let Some(interslot_updates) = args.slots_interslot_updates {
+        config.slots.interslot_updates = interslot_updates;
+    }
+    for kind in &args.subscribe {
+        if !config.subscribes(*kind) {
+            config.subscribe.push(*kind);
+        }
+    }
+    config
+        .accounts
+        .account
+        .extend(args.accounts_account.iter().cloned());
+    config
+        .accounts
+        .owner
+        .extend(args.accounts_owner.iter().cloned());
+
+    config.validate()?;
+    Ok(config)
 }
 
 #[tokio::main]
@@ -133,145 +490,234 @@ async fn main() -> Result<()> {
 
     let args = Args::parse();
 
+    let mut programs = match &args.programs {
+        Some(path) => ProgramRegistry::load(path)?,
+        None => ProgramRegistry::builtin(),
+    };
+    for key in &args.enable_program {
+        programs.set_enabled(key, true)?;
+    }
+    for key in &args.disable_program {
+        programs.set_enabled(key, false)?;
+    }
+    let programs = Arc::new(programs);
+    let watched = programs.enabled().count();
+    if watched == 0 {
+        bail!("no programs enabled");
+    }
+    eprintln!("Watching {} programs", watched);
+    let subscription = Arc::new(Subscription::new(
+        subscription_config(&args)?,
+        programs.clone(),
+    ));
+
     // Создаем Prometheus registry и метрики
-    let (metrics, registry) = Metrics::new()?;
+    let (metrics, registry) = Metrics::new(!args.no_legacy_metrics)?;
+
+    // Состояние стримов для /ready и /status; при воспроизведении записи стримов нет
+    let live_endpoints = match args.replay {
+        Some(_) => Vec::new(),
+        None => args
+            .endpoints
+            .iter()
+            .map(|spec| parse_endpoint(spec).0)
+            .collect(),
+    };
+    let status = Arc::new(StreamStatus::new(
+        live_endpoints,
+        (args.ready_max_silence_ms > 0).then_some(args.ready_max_silence_ms),
+    ));
 
     // Запускаем Prometheus metrics server
     let registry_clone = registry.clone();
     let metrics_port = args.metrics_port;
+    let status_clone = status.clone();
     tokio::spawn(async move {
-        start_metrics_server(registry_clone, metrics_port).await;
+        start_metrics_server(registry_clone, status_clone, metrics_port).await;
     });
 
-    println!("Connecting to Yellowstone gRPC endpoint: {}", args.endpoint);
+    // Админка меняет подписку без авторизации, поэтому поднимаем ее только по явному флагу
+    if let Some(addr) = args.admin_listen {
+        let subscription = subscription.clone();
+        tokio::spawn(async move {
+            start_admin_server(subscription, addr).await;
+        });
+    }
+
+    let (sinks, exporters) = slot_sinks(&args, &metrics)?;
+    let sink: Arc<dyn SlotSink> = Arc::new(sinks);
 
-    // Create client using builder pattern
-    let mut builder = GeyserGrpcClient::build_from_shared(args.endpoint.clone())?;
+    let eviction = EvictionPolicy {
+        max_age_ms: (args.evict_max_age_secs > 0)
+            .then_some(args.evict_max_age_secs.saturating_mul(1000)),
+        max_slot_distance: (args.evict_max_slot_distance > 0)
+            .then_some(args.evict_max_slot_distance),
+    };
 
-    if let Some(token) = args.x_token {
-        builder = builder.x_token(Some(token))?;
+    if let Some(path) = &args.replay {
+        // Эндпоинты берутся из записи, сравниваем их так же, как при живом подключении
+        let comparator = Arc::new(EndpointComparator::default());
+        let mut compared = 0;
+        let expect_block_meta = subscription.subscribes(UpdateKind::BlocksMeta);
+        let result = replay(path, args.replay_realtime, expect_block_meta, |name| {
+            // Сверх лимита эндпоинты воспроизводятся, но в сравнении не участвуют
+            let comparator = if compared < MAX_COMPARED_ENDPOINTS {
+                compared += 1;
+                Some(comparator.clone())
+            } else {
+                eprintln!(
+                    "[{}] Not comparing: recording has more than {} endpoints",
+                    name, MAX_COMPARED_ENDPOINTS
+                );
+                None
+            };
+            EndpointContext {
+                index: comparator
+                    .as_ref()
+                    .map_or(0, |c| c.add_endpoint(name.to_string())),
+                name: name.to_string(),
+                metrics: metrics.clone(),
+                programs: programs.clone(),
+                eviction,
+                summary_signatures: args.summary_signatures,
+                comparator,
+                recorder: None,
+                status: None,
+                sink: sink.clone(),
+            }
+        })
+        .await;
+        close_exporters(&exporters).await;
+        return result;
     }
 
-    // Configure TLS based on endpoint protocol
-    let builder = if args.endpoint.starts_with("https://") {
-        // For HTTPS endpoints, enable TLS
-        let tls_config = ClientTlsConfig::new().with_enabled_roots();
-
-        if args.insecure {
-            eprintln!(
-                "Warning: --insecure flag specified but certificate verification cannot be disabled"
-            );
-            eprintln!(
-                "If you have certificate issues, try adding the CA certificate to your system trust store"
-            );
-        }
+    let recorder = args
+        .record
+        .clone()
+        .map(|dir| Recorder::start(dir, args.record_max_file_mb.saturating_mul(1024 * 1024)))
+        .transpose()?;
+
+    if args.endpoints.len() > MAX_COMPARED_ENDPOINTS {
+        bail!(
+            "got {} endpoints, at most {} can be monitored at once",
+            args.endpoints.len(),
+            MAX_COMPARED_ENDPOINTS
+        );
+    }
 
-        builder.tls_config(tls_config)?
-    } else {
-        builder
+    if args.x_tokens.len() > args.endpoints.len() {
+        bail!(
+            "got {} --x-token values for {} endpoints",
+            args.x_tokens.len(),
+            args.endpoints.len()
+        );
+    }
+
+    let tls = TlsOptions {
+        ca_cert: args.ca_cert.clone(),
+        client_cert: args.client_cert.clone(),
+        client_key: args.client_key.clone(),
+        domain: args.tls_domain.clone(),
+        insecure: args.insecure,
     };
 
-    let mut client = builder
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .timeout(std::time::Duration::from_secs(10))
-        .connect()
-        .await?;
-
-    println!("Connected successfully!");
-
-    // Subscribe to transactions involving these AMM programs
-    let mut transactions = HashMap::new();
-    transactions.insert(
-        "amm_transactions".to_string(),
-        SubscribeRequestFilterTransactions {
-            vote: Some(false),
-            failed: Some(false),
-            signature: None,
-            account_include: get_accounts(),
-            account_exclude: vec![],
-            account_required: vec![],
-        },
-    );
-
-    // Empty accounts subscription
-    let accounts = HashMap::new();
-
-    let mut slots = HashMap::new();
-    slots.insert(
-        "client".to_string(),
-        SubscribeRequestFilterSlots {
-            filter_by_commitment: Some(false),
-            interslot_updates: Some(true),
-        },
-    );
-
-    let mut stream = client
-        .subscribe_once(SubscribeRequest {
-            slots,
-            accounts,
-            transactions,
-            transactions_status: HashMap::new(),
-            blocks: HashMap::new(),
-            blocks_meta: HashMap::new(),
-            entry: HashMap::new(),
-            commitment: Some(CommitmentLevel::Confirmed as i32),
-            accounts_data_slice: vec![],
-            ping: None,
-            from_slot: None,
-        })
-        .await?;
-
-    println!("Listening for updates...");
-
-    // HashMap для отслеживания слотов
-    let mut slot_trackers: HashMap<u64, SlotTracker> = HashMap::new();
-
-    while let Some(message) = stream.next().await {
-        match message {
-            Ok(msg) => {
-                match msg.update_oneof {
-                    Some(subscribe_update::UpdateOneof::Account(_account)) => {
-                        // Account updates are not subscribed to anymore
-                    }
-                    Some(subscribe_update::UpdateOneof::Slot(slot)) => {
-                        let status = SlotStatus::try_from(slot.status)?;
-                        if status == SlotStatus::SlotFinalized || status == SlotStatus::SlotDead {
-                            if let Some(tracker) = slot_trackers.remove(&slot.slot) {
-                                tracker.print_summary(status.as_str_name(), &metrics);
-                            }
-                        } else {
-                            let tracker = slot_trackers.entry(slot.slot).or_insert(
-                                SlotTracker::new(slot.slot, format!("slot_update_{:?}", status)),
-                            );
-                            tracker.update_status(status);
-                        }
-                    }
-                    Some(subscribe_update::UpdateOneof::Transaction(transaction)) => {
-                        if let Some(_tx_info) = &transaction.transaction {
-                            let tracker =
-                                slot_trackers
-                                    .entry(transaction.slot)
-                                    .or_insert(SlotTracker::new(
-                                        transaction.slot,
-                                        "transaction".to_string(),
-                                    ));
-                            tracker.apply_transaction();
-                        }
-                    }
-                    Some(subscribe_update::UpdateOneof::Ping(_)) => {
-                        println!("Ping received");
-                    }
-                    _ => {
-                        println!("Other update received");
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("Error receiving message: {}", e);
-                break;
+    let connections: Vec<ConnectionConfig> = args
+        .endpoints
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            let (name, endpoint) = parse_endpoint(spec);
+            let x_token = args.x_tokens.get(i).filter(|t| !t.is_empty()).cloned();
+            ConnectionConfig {
+                name,
+                endpoint,
+                x_token,
+                tls: tls.clone(),
             }
-        }
+        })
+        .collect();
+
+    if tls.is_set()
+        && let Some(plain) = connections
+            .iter()
+            .find(|c| !c.endpoint.starts_with("https://"))
+    {
+        bail!(
+            "TLS options were given but endpoint {} is not https://",
+            plain.endpoint
+        );
     }
 
-    Ok(())
+    // Под одним именем метрики, /status и сравнение разных эндпоинтов слились бы в одну серию
+    let mut names = HashSet::new();
+    if let Some(duplicate) = connections.iter().find(|c| !names.insert(c.name.as_str())) {
+        bail!(
+            "endpoint name {} is used more than once; name each endpoint with NAME=URL",
+            duplicate.name
+        );
+    }
+
+    let comparator = (connections.len() > 1).then(|| {
+        Arc::new(EndpointComparator::new(
+            connections.iter().map(|c| c.name.clone()).collect(),
+        ))
+    });
+
+    let reconnect = ReconnectConfig {
+        initial_backoff: Duration::from_millis(args.reconnect_initial_backoff_ms),
+        max_backoff: Duration::from_millis(args.reconnect_max_backoff_ms),
+        max_attempts: args.max_reconnect_attempts,
+    };
+    let keepalive = KeepaliveConfig {
+        ping_interval: (args.ping_interval_ms > 0)
+            .then(|| Duration::from_millis(args.ping_interval_ms)),
+        stall_timeout: (args.stall_timeout_ms > 0)
+            .then(|| Duration::from_millis(args.stall_timeout_ms)),
+    };
+    let queue = QueueConfig {
+        capacity: args.processing_queue_size as usize,
+        drop_when_full: args.drop_when_queue_full,
+    };
+
+    // Каждый эндпоинт читается в своей задаче со своими SlotTracker'ами
+    let mut tasks = JoinSet::new();
+    for (index, connection) in connections.into_iter().enumerate() {
+        let ctx = EndpointContext {
+            index,
+            name: connection.name.clone(),
+            metrics: metrics.clone(),
+            programs: programs.clone(),
+            eviction,
+            summary_signatures: args.summary_signatures,
+            comparator: comparator.clone(),
+            recorder: recorder.clone(),
+            status: Some(status.clone()),
+            sink: sink.clone(),
+        };
+        tasks.spawn(run_endpoint(
+            ctx,
+            connection,
+            reconnect.clone(),
+            keepalive.clone(),
+            queue,
+            subscription.clone(),
+        ));
+    }
+
+    let endpoints = async {
+        while let Some(result) = tasks.join_next().await {
+            result??;
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    let result = tokio::select! {
+        result = endpoints => result,
+        result = shutdown_signal() => {
+            eprintln!("Shutting down");
+            result
+        }
+    };
+    // Иначе текущий Parquet файл останется без футера
+    close_exporters(&exporters).await;
+    result
 }
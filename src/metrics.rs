@@ -1,94 +1,750 @@
+use crate::summary::{SlotSummary, us_to_ms};
 use anyhow::Result;
-use prometheus::{Counter, Histogram, HistogramOpts, Opts, Registry};
+use prometheus::{
+    Counter, CounterVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
 use yellowstone_grpc_proto::geyser::SlotStatus;
 
+// Пары стадий слота, интервалы между которыми попадают в гистограмму
+const STAGE_INTERVALS: &[(SlotStatus, SlotStatus)] = &[
+    (SlotStatus::SlotFirstShredReceived, SlotStatus::SlotCreatedBank),
+    (SlotStatus::SlotFirstShredReceived, SlotStatus::SlotCompleted),
+    (SlotStatus::SlotCreatedBank, SlotStatus::SlotCompleted),
+    (SlotStatus::SlotCompleted, SlotStatus::SlotProcessed),
+    (SlotStatus::SlotProcessed, SlotStatus::SlotConfirmed),
+    (SlotStatus::SlotConfirmed, SlotStatus::SlotFinalized),
+];
+
+// Стадии, относительно которых меряем первую и последнюю транзакцию слота
+const TX_RELATIVE_STAGES: &[SlotStatus] = &[
+    SlotStatus::SlotFirstShredReceived,
+    SlotStatus::SlotCreatedBank,
+    SlotStatus::SlotCompleted,
+    SlotStatus::SlotProcessed,
+    SlotStatus::SlotConfirmed,
+    SlotStatus::SlotFinalized,
+];
+
+const ALL_STATUSES: &[SlotStatus] = &[
+    SlotStatus::SlotProcessed,
+    SlotStatus::SlotConfirmed,
+    SlotStatus::SlotFinalized,
+    SlotStatus::SlotFirstShredReceived,
+    SlotStatus::SlotCompleted,
+    SlotStatus::SlotCreatedBank,
+    SlotStatus::SlotDead,
+];
+
+/// Short lowercase name of a slot status, as used in metric names and labels.
+pub fn status_label(status: SlotStatus) -> &'static str {
+    match status {
+        SlotStatus::SlotProcessed => "processed",
+        SlotStatus::SlotConfirmed => "confirmed",
+        SlotStatus::SlotFinalized => "finalized",
+        SlotStatus::SlotFirstShredReceived => "first_shred_received",
+        SlotStatus::SlotCompleted => "completed",
+        SlotStatus::SlotCreatedBank => "created_bank",
+        SlotStatus::SlotDead => "dead",
+    }
+}
+
 #[derive(Clone)]
 pub struct Metrics {
-    pub slot_duration_histogram: Histogram,
-    pub tx_by_status_counters: HashMap<String, Counter>,
+    pub slot_duration_histogram: HistogramVec,
+    pub slot_transactions: CounterVec,
+    pub slot_duplicate_transactions: IntCounterVec,
+    // Порядок доставки относительно индексов транзакций в блоке
+    pub slot_tx_out_of_order: IntCounterVec,
+    pub slot_tx_missing_indices: IntCounterVec,
+    pub slot_tx_index_span_histogram: HistogramVec,
+    // Сверка с BlockMeta
+    pub slot_executed_transactions: IntCounterVec,
+    pub slot_block_meta_missing: IntCounterVec,
+    // Старые счетчики slot_transactions_<status> для grafana-dashboard.json,
+    // пока дашборды не переведены на slot_transactions_total
+    pub legacy_tx_by_status_counters: Option<HashMap<&'static str, Counter>>,
+    // Таймлайн статусов слота
+    pub slot_stage_interval_histogram: HistogramVec,
+    pub slot_tx_relative_to_stage_histogram: HistogramVec,
+    // Задержка доставки сообщений (created_at -> локальное получение)
+    pub transport_latency_histogram: HistogramVec,
+    // Трекеры, которые так и не дошли до Finalized/Dead
+    pub slot_trackers_evicted: CounterVec,
+    pub slot_trackers_live: IntGaugeVec,
+    // Активность по DEX программам
+    pub program_transactions: CounterVec,
+    pub program_slot_duration_histogram: HistogramVec,
+    // Сравнение нескольких эндпоинтов
+    pub endpoint_first_slot_status: CounterVec,
+    pub endpoint_slot_status_lag_histogram: HistogramVec,
+    pub endpoint_first_transaction: CounterVec,
+    pub endpoint_transaction_lag_histogram: HistogramVec,
+    // Keepalive стрима
+    pub stream_ping_rtt_histogram: HistogramVec,
+    pub stream_stalls: CounterVec,
+    pub stream_messages: IntCounterVec,
+    pub stream_received_bytes: IntCounterVec,
+    pub stream_errors: IntCounterVec,
+    pub stream_reconnects: IntCounterVec,
+    pub stream_highest_slot: IntGaugeVec,
+    pub stream_slots_behind_processed: IntGaugeVec,
+    pub stream_message_interval_histogram: HistogramVec,
+    // Очередь между чтением стрима и обработкой
+    pub processing_queue_depth: IntGaugeVec,
+    pub processing_queue_dropped: IntCounterVec,
+    pub processing_queue_blocked: IntCounterVec,
+    pub processing_queue_delay_histogram: HistogramVec,
 }
 
+// Значение метки status для транзакций, пришедших до первого статуса слота
+pub const NO_STATUS_YET: &str = "no_status_yet";
+
+// Статусы, для которых считаем отставание от самого свежего processed слота
+const BEHIND_PROCESSED_STATUSES: &[SlotStatus] = &[
+    SlotStatus::SlotConfirmed,
+    SlotStatus::SlotFinalized,
+];
+
+// Buckets для длительности слота и активности программ в нем
+const SLOT_DURATION_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 25.0, 40.0, 60.0, 80.0, 100.0, 125.0, 150.0,
+    175.0, 200.0, 250.0, 300.0, 500.0,
+];
+
+// Buckets для отставания эндпоинта от самого быстрого
+const ENDPOINT_LAG_BUCKETS: &[f64] = &[
+    0.0, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0,
+    2000.0, 5000.0,
+];
+
 impl Metrics {
-    pub fn new() -> Result<(Self, Arc<Registry>)> {
+    /// With `legacy_names` the per-status `slot_transactions_<status>` counters
+    /// are registered too, alongside the labeled `slot_transactions_total`.
+    pub fn new(legacy_names: bool) -> Result<(Self, Arc<Registry>)> {
         let registry = Arc::new(Registry::new());
         
-        let slot_duration_histogram = Histogram::with_opts(
+        let slot_duration_histogram = HistogramVec::new(
             HistogramOpts::new(
                 "slot_duration_milliseconds",
                 "Duration from first to last transaction in a slot (milliseconds)"
             )
-            // Оптимизированные buckets для диапазона 1-200мс
-            .buckets(vec![
-                1.0, 2.0, 5.0, 10.0, 15.0, 25.0, 40.0, 60.0, 80.0, 100.0, 125.0, 150.0, 175.0, 200.0, 250.0, 300.0, 500.0
-            ])
+            // Оптимизированные buckets для диапазона 1-200мс, с долями миллисекунды снизу
+            .buckets(SLOT_DURATION_BUCKETS.to_vec()),
+            &["endpoint"],
         )?;
+        registry.register(Box::new(slot_duration_histogram.clone()))?;
 
-        // Создаем счетчики для каждого статуса + no_status_yet
-        let mut tx_by_status_counters = HashMap::new();
-        
-        // Добавляем счетчик для транзакций без статуса
-        let no_status_counter = Counter::with_opts(
+        // Транзакции завершенных слотов по статусу слота на момент прихода
+        let slot_transactions = CounterVec::new(
+            Opts::new(
+                "slot_transactions_total",
+                "Number of transactions in completed slots by the slot status they arrived in"
+            ),
+            &["status", "endpoint"],
+        )?;
+        registry.register(Box::new(slot_transactions.clone()))?;
+
+        // Повторно доставленные транзакции (та же сигнатура в том же слоте)
+        let slot_duplicate_transactions = IntCounterVec::new(
             Opts::new(
-                "slot_transactions_no_status_yet",
-                "Number of transactions in slots without status yet"
+                "slot_duplicate_transactions_total",
+                "Number of transactions delivered again with a signature already seen in the slot"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_duplicate_transactions.clone()))?;
+
+        let slot_tx_out_of_order = IntCounterVec::new(
+            Opts::new(
+                "slot_tx_out_of_order_total",
+                "Number of transactions that arrived after a transaction with a higher index in the same block"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_tx_out_of_order.clone()))?;
+
+        let slot_tx_missing_indices = IntCounterVec::new(
+            Opts::new(
+                "slot_tx_missing_indices_total",
+                "Number of block indices between the lowest and highest observed transaction index that never arrived"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_tx_missing_indices.clone()))?;
+
+        let slot_tx_index_span_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "slot_tx_index_span_milliseconds",
+                "Arrival of the highest-index transaction of a slot minus arrival of the lowest-index one (milliseconds)"
             )
+            .buckets(vec![
+                -500.0, -100.0, -10.0, -1.0, 0.0, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0,
+                200.0, 400.0, 1000.0
+            ]),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_tx_index_span_histogram.clone()))?;
+
+        let slot_executed_transactions = IntCounterVec::new(
+            Opts::new(
+                "slot_executed_transactions_total",
+                "Number of transactions executed in completed slots according to their block meta, regardless of the transaction filter"
+            ),
+            &["endpoint"],
         )?;
-        registry.register(Box::new(no_status_counter.clone()))?;
-        tx_by_status_counters.insert("no_status_yet".to_string(), no_status_counter);
-
-        // Добавляем счетчики для всех возможных SlotStatus
-        let status_variants = [
-            (SlotStatus::SlotProcessed, "processed"),
-            (SlotStatus::SlotConfirmed, "confirmed"), 
-            (SlotStatus::SlotFinalized, "finalized"),
-            (SlotStatus::SlotFirstShredReceived, "first_shred_received"),
-            (SlotStatus::SlotCompleted, "completed"),
-            (SlotStatus::SlotCreatedBank, "created_bank"),
-            (SlotStatus::SlotDead, "dead"),
-        ];
-
-        for (status, status_name) in status_variants {
-            let counter = Counter::with_opts(
+        registry.register(Box::new(slot_executed_transactions.clone()))?;
+
+        let slot_block_meta_missing = IntCounterVec::new(
+            Opts::new(
+                "slot_block_meta_missing_total",
+                "Number of finalized slots whose block meta never arrived despite a blocks_meta subscription"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_block_meta_missing.clone()))?;
+
+        let legacy_tx_by_status_counters = if legacy_names {
+            let mut counters = HashMap::new();
+            let no_status_counter = Counter::with_opts(
                 Opts::new(
-                    &format!("slot_transactions_{}", status_name),
-                    &format!("Number of transactions in {} slots", status_name)
+                    "slot_transactions_no_status_yet",
+                    "Number of transactions in slots without status yet (deprecated, use slot_transactions_total)"
                 )
             )?;
-            registry.register(Box::new(counter.clone()))?;
-            tx_by_status_counters.insert(format!("{:?}", status), counter);
-        }
+            registry.register(Box::new(no_status_counter.clone()))?;
+            counters.insert(NO_STATUS_YET, no_status_counter);
 
-        registry.register(Box::new(slot_duration_histogram.clone()))?;
+            for status in ALL_STATUSES {
+                let status_name = status_label(*status);
+                let counter = Counter::with_opts(
+                    Opts::new(
+                        format!("slot_transactions_{}", status_name),
+                        format!("Number of transactions in {} slots (deprecated, use slot_transactions_total)", status_name)
+                    )
+                )?;
+                registry.register(Box::new(counter.clone()))?;
+                counters.insert(status_name, counter);
+            }
+            Some(counters)
+        } else {
+            None
+        };
+
+        let slot_stage_interval_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "slot_stage_interval_milliseconds",
+                "Time between two status updates of the same slot (milliseconds)"
+            )
+            .buckets(vec![
+                0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 200.0, 300.0, 400.0, 500.0, 750.0, 1000.0,
+                2000.0, 5000.0, 10000.0, 15000.0, 20000.0, 30000.0, 60000.0
+            ]),
+            &["from", "to", "endpoint"],
+        )?;
+        registry.register(Box::new(slot_stage_interval_histogram.clone()))?;
+
+        let slot_tx_relative_to_stage_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "slot_tx_relative_to_stage_milliseconds",
+                "Arrival of the first/last transaction of a slot relative to a status update; negative means before it (milliseconds)"
+            )
+            .buckets(vec![
+                -20000.0, -5000.0, -1000.0, -500.0, -200.0, -100.0, -50.0, -10.0, -1.0, -0.1, 0.0,
+                0.1, 1.0, 10.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 5000.0, 20000.0
+            ]),
+            &["stage", "edge", "endpoint"],
+        )?;
+        registry.register(Box::new(slot_tx_relative_to_stage_histogram.clone()))?;
+
+        let transport_latency_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "message_transport_latency_milliseconds",
+                "Local receive time minus the server created_at of an update; negative values mean clock skew (milliseconds)"
+            )
+            .buckets(vec![
+                -100.0, -50.0, -20.0, -10.0, -5.0, -1.0, 0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0,
+                100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0
+            ]),
+            &["update_type", "endpoint"],
+        )?;
+        registry.register(Box::new(transport_latency_histogram.clone()))?;
+
+        let slot_trackers_evicted = CounterVec::new(
+            Opts::new(
+                "slot_trackers_evicted_total",
+                "Number of slot trackers flushed without reaching Finalized or Dead"
+            ),
+            &["endpoint", "reason"],
+        )?;
+        registry.register(Box::new(slot_trackers_evicted.clone()))?;
+
+        let slot_trackers_live = IntGaugeVec::new(
+            Opts::new(
+                "slot_trackers_live",
+                "Number of slot trackers currently in flight"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(slot_trackers_live.clone()))?;
+
+        let program_transactions = CounterVec::new(
+            Opts::new(
+                "program_transactions_total",
+                "Number of transactions touching a program, counted when the slot completes"
+            ),
+            &["program", "code", "endpoint"],
+        )?;
+        registry.register(Box::new(program_transactions.clone()))?;
+
+        let program_slot_duration_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "program_slot_duration_milliseconds",
+                "Duration from first to last transaction of a program within a slot (milliseconds)"
+            )
+            .buckets(SLOT_DURATION_BUCKETS.to_vec()),
+            &["program", "code", "endpoint"],
+        )?;
+        registry.register(Box::new(program_slot_duration_histogram.clone()))?;
+
+        let endpoint_first_slot_status = CounterVec::new(
+            Opts::new(
+                "endpoint_first_slot_status_total",
+                "Number of slot status updates an endpoint delivered before all other endpoints"
+            ),
+            &["endpoint", "status"],
+        )?;
+        registry.register(Box::new(endpoint_first_slot_status.clone()))?;
+
+        let endpoint_slot_status_lag_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "endpoint_slot_status_lag_milliseconds",
+                "How long after the fastest endpoint a slot status update arrived (milliseconds)"
+            )
+            .buckets(ENDPOINT_LAG_BUCKETS.to_vec()),
+            &["endpoint", "status"],
+        )?;
+        registry.register(Box::new(endpoint_slot_status_lag_histogram.clone()))?;
+
+        let endpoint_first_transaction = CounterVec::new(
+            Opts::new(
+                "endpoint_first_transaction_total",
+                "Number of transactions an endpoint delivered before all other endpoints"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(endpoint_first_transaction.clone()))?;
+
+        let endpoint_transaction_lag_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "endpoint_transaction_lag_milliseconds",
+                "How long after the fastest endpoint a transaction arrived (milliseconds)"
+            )
+            .buckets(ENDPOINT_LAG_BUCKETS.to_vec()),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(endpoint_transaction_lag_histogram.clone()))?;
+
+        let stream_ping_rtt_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "stream_ping_rtt_milliseconds",
+                "Round-trip time of a ping sent over the subscribe stream until its pong (milliseconds)"
+            )
+            .buckets(vec![
+                0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0
+            ]),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(stream_ping_rtt_histogram.clone()))?;
+
+        let stream_stalls = CounterVec::new(
+            Opts::new(
+                "stream_stalls_total",
+                "Number of times a subscribe stream was dropped because no update arrived within the stall timeout"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(stream_stalls.clone()))?;
+
+        let stream_messages = IntCounterVec::new(
+            Opts::new(
+                "stream_messages_total",
+                "Number of messages received over the subscribe stream by update type"
+            ),
+            &["endpoint", "update_type"],
+        )?;
+        registry.register(Box::new(stream_messages.clone()))?;
+
+        let stream_received_bytes = IntCounterVec::new(
+            Opts::new(
+                "stream_received_bytes_total",
+                "Encoded size of messages received over the subscribe stream (bytes)"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(stream_received_bytes.clone()))?;
+
+        let stream_errors = IntCounterVec::new(
+            Opts::new(
+                "stream_errors_total",
+                "Number of connection and stream failures by gRPC status code"
+            ),
+            &["endpoint", "code"],
+        )?;
+        registry.register(Box::new(stream_errors.clone()))?;
+
+        let stream_reconnects = IntCounterVec::new(
+            Opts::new(
+                "stream_reconnects_total",
+                "Number of reconnect attempts"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(stream_reconnects.clone()))?;
+
+        let stream_highest_slot = IntGaugeVec::new(
+            Opts::new(
+                "stream_highest_slot",
+                "Highest slot seen in a slot update, by slot status"
+            ),
+            &["endpoint", "status"],
+        )?;
+        registry.register(Box::new(stream_highest_slot.clone()))?;
+
+        let stream_slots_behind_processed = IntGaugeVec::new(
+            Opts::new(
+                "stream_slots_behind_processed",
+                "How many slots the highest slot with this status is behind the highest processed slot"
+            ),
+            &["endpoint", "status"],
+        )?;
+        registry.register(Box::new(stream_slots_behind_processed.clone()))?;
+
+        let stream_message_interval_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "stream_message_interval_milliseconds",
+                "Time between consecutive messages on the subscribe stream (milliseconds)"
+            )
+            .buckets(vec![
+                0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 400.0, 1000.0, 2000.0, 5000.0, 10000.0
+            ]),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(stream_message_interval_histogram.clone()))?;
+
+        let processing_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "processing_queue_depth",
+                "Number of received updates waiting to be processed"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(processing_queue_depth.clone()))?;
+
+        let processing_queue_dropped = IntCounterVec::new(
+            Opts::new(
+                "processing_queue_dropped_total",
+                "Number of received updates dropped because the processing queue was full"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(processing_queue_dropped.clone()))?;
+
+        let processing_queue_blocked = IntCounterVec::new(
+            Opts::new(
+                "processing_queue_blocked_total",
+                "Number of times reading the stream waited for room in the processing queue"
+            ),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(processing_queue_blocked.clone()))?;
+
+        let processing_queue_delay_histogram = HistogramVec::new(
+            HistogramOpts::new(
+                "processing_queue_delay_milliseconds",
+                "Time an update spent in the processing queue after being received (milliseconds)"
+            )
+            .buckets(vec![
+                0.01, 0.05, 0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 5000.0
+            ]),
+            &["endpoint"],
+        )?;
+        registry.register(Box::new(processing_queue_delay_histogram.clone()))?;
 
         let metrics = Metrics {
             slot_duration_histogram,
-            tx_by_status_counters,
+            slot_transactions,
+            slot_duplicate_transactions,
+            slot_tx_out_of_order,
+            slot_tx_missing_indices,
+            slot_tx_index_span_histogram,
+            slot_executed_transactions,
+            slot_block_meta_missing,
+            legacy_tx_by_status_counters,
+            slot_stage_interval_histogram,
+            slot_tx_relative_to_stage_histogram,
+            transport_latency_histogram,
+            slot_trackers_evicted,
+            slot_trackers_live,
+            program_transactions,
+            program_slot_duration_histogram,
+            endpoint_first_slot_status,
+            endpoint_slot_status_lag_histogram,
+            endpoint_first_transaction,
+            endpoint_transaction_lag_histogram,
+            stream_ping_rtt_histogram,
+            stream_stalls,
+            stream_messages,
+            stream_received_bytes,
+            stream_errors,
+            stream_reconnects,
+            stream_highest_slot,
+            stream_slots_behind_processed,
+            stream_message_interval_histogram,
+            processing_queue_depth,
+            processing_queue_dropped,
+            processing_queue_blocked,
+            processing_queue_delay_histogram,
         };
 
         Ok((metrics, registry))
     }
 
-    pub fn record_slot_finalized(&self, duration_ms: u64, tx_initiated_count: u64, tx_counts: &HashMap<SlotStatus, u64>) {
-        self.slot_duration_histogram.observe(duration_ms as f64);
-        
-        // Записываем транзакции без статуса
-        if tx_initiated_count > 0 {
-            if let Some(counter) = self.tx_by_status_counters.get("no_status_yet") {
-                counter.inc_by(tx_initiated_count as f64);
+    /// Records a finalized or dead slot; evicted slots only bump the eviction
+    /// and duplicate counters, so they don't skew the histograms of completed slots.
+    pub fn record_slot_summary(&self, summary: &SlotSummary) {
+        let endpoint = summary.endpoint.as_str();
+        // Повторы считаем и для вытесненных слотов: это свойство стрима, а не слота
+        if summary.duplicate_txs > 0 {
+            self.slot_duplicate_transactions
+                .with_label_values(&[endpoint])
+                .inc_by(summary.duplicate_txs);
+        }
+        if let Some(reason) = summary.eviction_reason {
+            self.record_slot_evicted(endpoint, reason.as_str());
+            return;
+        }
+
+        self.slot_duration_histogram
+            .with_label_values(&[endpoint])
+            .observe(us_to_ms(summary.duration_us));
+
+        // Транзакции без статуса и по статусам
+        for &(status, count) in &summary.tx_by_status {
+            if count == 0 {
+                continue;
+            }
+            let status = status.map_or(NO_STATUS_YET, status_label);
+            self.slot_transactions
+                .with_label_values(&[status, endpoint])
+                .inc_by(count as f64);
+            if let Some(counter) = self
+                .legacy_tx_by_status_counters
+                .as_ref()
+                .and_then(|counters| counters.get(status))
+            {
+                counter.inc_by(count as f64);
+            }
+        }
+
+        for program in &summary.tx_by_program {
+            let labels = [program.program.as_str(), program.code.as_str(), endpoint];
+            self.program_transactions
+                .with_label_values(&labels)
+                .inc_by(program.tx_count as f64);
+            self.program_slot_duration_histogram
+                .with_label_values(&labels)
+                .observe(us_to_ms(program.last_tx_at_us.saturating_sub(program.first_tx_at_us)));
+        }
+
+        self.record_slot_timeline(summary);
+        self.record_tx_order(summary);
+
+        if let Some(block_meta) = &summary.block_meta {
+            self.slot_executed_transactions
+                .with_label_values(&[endpoint])
+                .inc_by(block_meta.executed_transaction_count);
+        }
+        if summary.block_meta_missing {
+            self.slot_block_meta_missing
+                .with_label_values(&[endpoint])
+                .inc();
+        }
+    }
+
+    fn record_tx_order(&self, summary: &SlotSummary) {
+        let Some(tx_index) = &summary.tx_index else {
+            return;
+        };
+        let endpoint = summary.endpoint.as_str();
+        self.slot_tx_out_of_order
+            .with_label_values(&[endpoint])
+            .inc_by(tx_index.out_of_order_txs);
+        self.slot_tx_missing_indices
+            .with_label_values(&[endpoint])
+            .inc_by(tx_index.missing_indices);
+        self.slot_tx_index_span_histogram
+            .with_label_values(&[endpoint])
+            .observe(tx_index.first_to_last_index_us as f64 / 1000.0);
+    }
+
+    fn record_slot_timeline(&self, summary: &SlotSummary) {
+        let endpoint = summary.endpoint.as_str();
+        for &(from, to) in STAGE_INTERVALS {
+            if let (Some(from_ts), Some(to_ts)) = (summary.status_ts(from), summary.status_ts(to))
+                && to_ts >= from_ts
+            {
+                self.slot_stage_interval_histogram
+                    .with_label_values(&[status_label(from), status_label(to), endpoint])
+                    .observe(us_to_ms(to_ts - from_ts));
             }
         }
 
-        // Записываем транзакции по статусам
-        for (status, count) in tx_counts {
-            if *count > 0 {
-                let status_key = format!("{:?}", status);
-                if let Some(counter) = self.tx_by_status_counters.get(&status_key) {
-                    counter.inc_by(*count as f64);
+        for &stage in TX_RELATIVE_STAGES {
+            let Some(stage_ts) = summary.status_ts(stage) else {
+                continue;
+            };
+            for (edge, tx_ts) in [("first", summary.first_tx_at_us), ("last", summary.last_tx_at_us)] {
+                if let Some(tx_ts) = tx_ts {
+                    self.slot_tx_relative_to_stage_histogram
+                        .with_label_values(&[status_label(stage), edge, endpoint])
+                        .observe((tx_ts as i64 - stage_ts as i64) as f64 / 1000.0);
                 }
             }
         }
     }
-}
 
+    pub fn record_transport_latency(&self, update_type: &str, endpoint: &str, latency_ms: f64) {
+        self.transport_latency_histogram
+            .with_label_values(&[update_type, endpoint])
+            .observe(latency_ms);
+    }
+
+    fn record_slot_evicted(&self, endpoint: &str, reason: &str) {
+        self.slot_trackers_evicted
+            .with_label_values(&[endpoint, reason])
+            .inc();
+    }
+
+    pub fn set_live_slot_trackers(&self, endpoint: &str, count: usize) {
+        self.slot_trackers_live
+            .with_label_values(&[endpoint])
+            .set(count as i64);
+    }
+
+    pub fn record_first_slot_status(&self, endpoint: &str, status: SlotStatus) {
+        self.endpoint_first_slot_status
+            .with_label_values(&[endpoint, status_label(status)])
+            .inc();
+    }
+
+    pub fn record_slot_status_lag(&self, endpoint: &str, status: SlotStatus, lag_us: u64) {
+        self.endpoint_slot_status_lag_histogram
+            .with_label_values(&[endpoint, status_label(status)])
+            .observe(lag_us as f64 / 1000.0);
+    }
+
+    pub fn record_first_transaction(&self, endpoint: &str) {
+        self.endpoint_first_transaction
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn record_transaction_lag(&self, endpoint: &str, lag_us: u64) {
+        self.endpoint_transaction_lag_histogram
+            .with_label_values(&[endpoint])
+            .observe(lag_us as f64 / 1000.0);
+    }
+
+    pub fn record_ping_rtt(&self, endpoint: &str, rtt_ms: f64) {
+        self.stream_ping_rtt_histogram
+            .with_label_values(&[endpoint])
+            .observe(rtt_ms);
+    }
+
+    pub fn record_stream_stall(&self, endpoint: &str) {
+        self.stream_stalls
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn record_stream_message(&self, endpoint: &str, update_type: &str, bytes: usize) {
+        self.stream_messages
+            .with_label_values(&[endpoint, update_type])
+            .inc();
+        self.stream_received_bytes
+            .with_label_values(&[endpoint])
+            .inc_by(bytes as u64);
+    }
+
+    pub fn record_message_interval(&self, endpoint: &str, interval_ms: f64) {
+        self.stream_message_interval_histogram
+            .with_label_values(&[endpoint])
+            .observe(interval_ms);
+    }
+
+    pub fn record_stream_error(&self, endpoint: &str, code: &str) {
+        self.stream_errors
+            .with_label_values(&[endpoint, code])
+            .inc();
+    }
+
+    pub fn record_reconnect(&self, endpoint: &str) {
+        self.stream_reconnects
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    /// Raises the highest slot seen with `status` and refreshes how far
+    /// confirmed and finalized are behind processed.
+    pub fn record_slot_seen(&self, endpoint: &str, slot: u64, status: SlotStatus) {
+        let highest = self.stream_highest_slot
+            .with_label_values(&[endpoint, status_label(status)]);
+        if slot as i64 > highest.get() {
+            highest.set(slot as i64);
+        }
+
+        let processed = self.stream_highest_slot
+            .with_label_values(&[endpoint, status_label(SlotStatus::SlotProcessed)])
+            .get();
+        if processed == 0 {
+            return;
+        }
+        for &behind_status in BEHIND_PROCESSED_STATUSES {
+            let highest = self.stream_highest_slot
+                .with_label_values(&[endpoint, status_label(behind_status)])
+                .get();
+            // Пока статус не встречался, отставание не показываем
+            if highest > 0 {
+                self.stream_slots_behind_processed
+                    .with_label_values(&[endpoint, status_label(behind_status)])
+                    .set(processed - highest);
+            }
+        }
+    }
+
+    pub fn set_queue_depth(&self, endpoint: &str, depth: usize) {
+        self.processing_queue_depth
+            .with_label_values(&[endpoint])
+            .set(depth as i64);
+    }
+
+    pub fn record_queue_dropped(&self, endpoint: &str) {
+        self.processing_queue_dropped
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn record_queue_blocked(&self, endpoint: &str) {
+        self.processing_queue_blocked
+            .with_label_values(&[endpoint])
+            .inc();
+    }
+
+    pub fn record_queue_delay(&self, endpoint: &str, delay_ms: f64) {
+        self.processing_queue_delay_histogram
+            .with_label_values(&[endpoint])
+            .observe(delay_ms);
+    }
+}
\ No newline at end of file
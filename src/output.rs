@@ -0,0 +1,74 @@
+use crate::summary::SlotSummary;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// How slot summaries are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// One human-readable line per slot.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Where slot summaries go: stdout or an append-only file, shared by all
+/// endpoint tasks.
+#[derive(Clone)]
+pub struct SummaryOutput {
+    format: OutputFormat,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl std::fmt::Debug for SummaryOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SummaryOutput")
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SummaryOutput {
+    pub fn stdout(format: OutputFormat) -> Self {
+        Self {
+            format,
+            writer: Arc::new(Mutex::new(Box::new(std::io::stdout()))),
+        }
+    }
+
+    /// Appends to `path`, creating it if needed.
+    pub fn file(path: &Path, format: OutputFormat) -> Result<Self> {
+        let file: File = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open output file {}", path.display()))?;
+        Ok(Self {
+            format,
+            writer: Arc::new(Mutex::new(Box::new(BufWriter::new(file)))),
+        })
+    }
+
+    pub fn write(&self, summary: &SlotSummary) {
+        let line = match self.format {
+            OutputFormat::Text => summary.text_line(),
+            OutputFormat::Json => match serde_json::to_string(summary) {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("Failed to serialize slot {} summary: {}", summary.slot, e);
+                    return;
+                }
+            },
+        };
+
+        // Сбрасываем после каждой записи: слотов несколько в секунду, а хвост терять не хотим
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|()| writer.flush()) {
+            eprintln!("Failed to write slot summary: {}", e);
+        }
+    }
+}
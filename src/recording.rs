@@ -0,0 +1,301 @@
+use crate::stream::{EndpointContext, apply_update, record_transport_latency};
+use crate::tracker::SlotTrackerSet;
+use anyhow::{Context, Result, bail};
+use prost::Message;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use yellowstone_grpc_proto::geyser::SubscribeUpdate;
+
+// Сколько обновлений может ждать записи, прежде чем начнем их отбрасывать
+const RECORDER_QUEUE_SIZE: usize = 65536;
+
+// Больше не бывает даже у обновлений с крупными транзакциями; длина больше
+// значит, что файл поврежден, и выделять под нее память нельзя
+const MAX_RECORD_BYTES: usize = 64 * 1024 * 1024;
+
+/// One `SubscribeUpdate` as it was received, stored length-delimited on disk.
+#[derive(Clone, PartialEq, Message)]
+pub struct RecordedUpdate {
+    /// Local receive time, microseconds since the Unix epoch.
+    #[prost(uint64, tag = "1")]
+    pub received_at_us: u64,
+    /// Name of the endpoint the update came from.
+    #[prost(string, tag = "2")]
+    pub endpoint: String,
+    #[prost(message, optional, tag = "3")]
+    pub update: Option<SubscribeUpdate>,
+}
+
+/// Handle for queueing updates to the background writer.
+#[derive(Clone)]
+pub struct Recorder {
+    tx: mpsc::Sender<RecordedUpdate>,
+    // Обновления, которые так и не попали в файл
+    dropped: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Starts a writer that appends to `updates-<unix ms>.bin` files in `dir`,
+    /// switching to a new file once the current one exceeds `max_file_bytes`.
+    pub fn start(dir: PathBuf, max_file_bytes: u64) -> Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create recording directory {}", dir.display()))?;
+        let (tx, mut rx) = mpsc::channel::<RecordedUpdate>(RECORDER_QUEUE_SIZE);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer_dropped = dropped.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut writer = RotatingWriter::new(dir, max_file_bytes);
+            while let Some(record) = rx.blocking_recv() {
+                // Сбрасываем буфер, когда очередь опустела, чтобы не терять хвост при остановке
+                let result = writer.write(&record).and_then(|()| {
+                    if rx.is_empty() {
+                        writer.flush()?;
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    // Файл бросаем, следующее обновление начнет новый
+                    writer.abandon();
+                    let dropped = writer_dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    eprintln!(
+                        "Failed to write recording: {:#} ({} updates dropped so far)",
+                        e, dropped
+                    );
+                }
+            }
+            if let Err(e) = writer.flush() {
+                eprintln!("Failed to flush recording: {:#}", e);
+            }
+        });
+
+        Ok(Self { tx, dropped })
+    }
+
+    /// Queues an update without waiting; drops it if the writer falls behind.
+    pub fn record(&self, endpoint: &str, received_at_us: u64, update: SubscribeUpdate) {
+        let record = RecordedUpdate {
+            received_at_us,
+            endpoint: endpoint.to_string(),
+            update: Some(update),
+        };
+        let reason = match self.tx.try_send(record) {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(_)) => "queue is full",
+            Err(mpsc::error::TrySendError::Closed(_)) => "writer has stopped",
+        };
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        eprintln!(
+            "Recorder {}, dropping update from {} ({} updates dropped so far)",
+            reason, endpoint, dropped
+        );
+    }
+}
+
+struct RotatingWriter {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    file: Option<BufWriter<File>>,
+    file_bytes: u64,
+    buf: Vec<u8>,
+}
+
+impl RotatingWriter {
+    fn new(dir: PathBuf, max_file_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_file_bytes,
+            file: None,
+            file_bytes: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, record: &RecordedUpdate) -> Result<()> {
+        if self.file.is_none() || self.file_bytes >= self.max_file_bytes {
+            self.rotate(record.received_at_us)?;
+        }
+
+        self.buf.clear();
+        record.encode_length_delimited(&mut self.buf)?;
+        let file = self.file.as_mut().expect("file is opened by rotate");
+        file.write_all(&self.buf)?;
+        self.file_bytes += self.buf.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self, received_at_us: u64) -> Result<()> {
+        self.flush()?;
+        let path = self
+            .dir
+            .join(format!("updates-{:013}.bin", received_at_us / 1000));
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        eprintln!("Recording updates to {}", path.display());
+        self.file = Some(BufWriter::new(file));
+        self.file_bytes = 0;
+        Ok(())
+    }
+
+    /// Forgets the current file after a failed write; the next write opens a new one.
+    fn abandon(&mut self) {
+        self.file = None;
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Recording files to replay: `path` itself, or every `.bin` file in it in name order.
+pub fn recording_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)
+        .with_context(|| format!("failed to read recording directory {}", path.display()))?
+    {
+        let file = entry?.path();
+        if file.extension().is_some_and(|ext| ext == "bin") {
+            files.push(file);
+        }
+    }
+    if files.is_empty() {
+        bail!("no .bin recordings in {}", path.display());
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads length-delimited `RecordedUpdate`s from one recording file.
+pub struct RecordingReader {
+    reader: BufReader<File>,
+    buf: Vec<u8>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(Self {
+            reader: BufReader::new(file),
+            buf: Vec::new(),
+        })
+    }
+
+    /// Returns `None` at the end of the file. A record cut short by a crash is
+    /// treated as the end as well.
+    pub fn next_record(&mut self) -> Result<Option<RecordedUpdate>> {
+        // Длина записи - varint, не более 10 байт
+        let mut len_buf = Vec::with_capacity(10);
+        loop {
+            let mut byte = [0u8; 1];
+            match self.reader.read_exact(&mut byte) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+            len_buf.push(byte[0]);
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            if len_buf.len() == 10 {
+                bail!("corrupt record length in recording");
+            }
+        }
+        let len = prost::decode_length_delimiter(len_buf.as_slice())?;
+        if len > MAX_RECORD_BYTES {
+            bail!(
+                "corrupt record length in recording: {} bytes, at most {} expected",
+                len,
+                MAX_RECORD_BYTES
+            );
+        }
+
+        self.buf.resize(len, 0);
+        match self.reader.read_exact(&mut self.buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Some(RecordedUpdate::decode(self.buf.as_slice())?))
+    }
+}
+
+/// Feeds a recording through the same tracker and metrics pipeline as the
+/// live stream. With `realtime` the original gaps between updates are kept,
+/// otherwise updates are applied as fast as they can be read.
+/// `expect_block_meta` tells whether the recording was made with a
+/// `blocks_meta` subscription, so that slots without block meta are reported.
+pub async fn replay(
+    path: &Path,
+    realtime: bool,
+    expect_block_meta: bool,
+    mut new_context: impl FnMut(&str) -> EndpointContext,
+) -> Result<()> {
+    let mut streams: HashMap<String, (EndpointContext, SlotTrackerSet)> = HashMap::new();
+    let mut clock: Option<(u64, Instant)> = None;
+    let mut updates = 0u64;
+    let mut failed = 0u64;
+
+    for file in recording_files(path)? {
+        eprintln!("Replaying {}", file.display());
+        let mut reader = RecordingReader::open(&file)?;
+
+        while let Some(record) = reader.next_record()? {
+            let Some(update) = record.update else {
+                continue;
+            };
+
+            if realtime {
+                let (first_us, started) =
+                    *clock.get_or_insert((record.received_at_us, Instant::now()));
+                let offset = Duration::from_micros(record.received_at_us.saturating_sub(first_us));
+                tokio::time::sleep_until((started + offset).into()).await;
+            }
+
+            let (ctx, trackers) = streams.entry(record.endpoint.clone()).or_insert_with(|| {
+                let ctx = new_context(&record.endpoint);
+                let mut trackers =
+                    SlotTrackerSet::new(ctx.name.clone(), ctx.programs.clone(), ctx.eviction);
+                trackers.expect_block_meta(expect_block_meta);
+                trackers.include_signatures(ctx.summary_signatures);
+                (ctx, trackers)
+            });
+            record_transport_latency(ctx, &update, record.received_at_us);
+            // Как и в живом стриме, одно битое обновление не останавливает обработку
+            if let Err(e) = apply_update(ctx, trackers, &update, record.received_at_us) {
+                eprintln!("[{}] Failed to process update: {:#}", ctx.name, e);
+                failed += 1;
+            }
+            updates += 1;
+        }
+    }
+
+    let in_flight: usize = streams
+        .values()
+        .map(|(_, trackers)| trackers.in_flight())
+        .sum();
+    eprintln!(
+        "Replay finished: {} updates from {} endpoints, {} failed to process, {} slots still in flight",
+        updates,
+        streams.len(),
+        failed,
+        in_flight
+    );
+    Ok(())
+}
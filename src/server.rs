@@ -1,11 +1,32 @@
-use axum::{extract::State, http::StatusCode, routing::get, Router};
+use crate::accounts::Program;
+use crate::status::StreamStatus;
+use crate::subscription::Subscription;
+use axum::{extract::State, http::StatusCode, routing::{get, post}, Json, Router};
 use prometheus::{Encoder, Registry, TextEncoder};
+use serde::Deserialize;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 
-async fn metrics_handler(State(registry): State<Arc<Registry>>) -> Result<String, StatusCode> {
+#[derive(Clone)]
+struct AppState {
+    registry: Arc<Registry>,
+    status: Arc<StreamStatus>,
+}
+
+/// Body of `POST /subscription/programs`. `add` takes new registry entries,
+/// `enable` and `disable` select programs by id, code or name.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProgramsUpdate {
+    add: Vec<Program>,
+    enable: Vec<String>,
+    disable: Vec<String>,
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Result<String, StatusCode> {
     let encoder = TextEncoder::new();
-    let metric_families = registry.gather();
+    let metric_families = state.registry.gather();
     let mut buffer = Vec::new();
     encoder.encode(&metric_families, &mut buffer)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -17,13 +38,75 @@ async fn health_handler() -> &'static str {
     "OK"
 }
 
-pub async fn start_metrics_server(registry: Arc<Registry>, port: u16) {
+// 503, пока хотя бы один стрим не подписан или молчит слишком долго
+async fn ready_handler(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    if StreamStatus::is_ready(&state.status.snapshot()) {
+        (StatusCode::OK, "READY")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "NOT READY")
+    }
+}
+
+async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let endpoints = state.status.snapshot();
+    Json(serde_json::json!({
+        "ready": StreamStatus::is_ready(&endpoints),
+        "endpoints": endpoints,
+    }))
+}
+
+async fn subscription_handler(
+    State(subscription): State<Arc<Subscription>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let programs = subscription.programs();
+    serde_json::to_value(subscription.view(&programs))
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn update_programs_handler(
+    State(subscription): State<Arc<Subscription>>,
+    Json(update): Json<ProgramsUpdate>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let added: Vec<String> = update.add.iter().map(|p| p.code.clone()).collect();
+    let programs = subscription
+        .update_programs(update.add, &update.enable, &update.disable)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    eprintln!(
+        "Programs updated via admin API: new {:?} +{:?} -{:?}, {} enabled",
+        added,
+        update.enable,
+        update.disable,
+        programs.enabled().count()
+    );
+    serde_json::to_value(subscription.view(&programs))
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Serves metrics, liveness, readiness and stream status.
+pub async fn start_metrics_server(registry: Arc<Registry>, status: Arc<StreamStatus>, port: u16) {
     let app = Router::new()
         .route("/metrics", get(metrics_handler))
         .route("/health", get(health_handler))
-        .with_state(registry);
+        .route("/ready", get(ready_handler))
+        .route("/status", get(status_handler))
+        .with_state(AppState { registry, status });
 
     let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await.unwrap();
-    println!("Prometheus metrics server listening on port {}", port);
+    eprintln!("Prometheus metrics server listening on port {}", port);
     axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+}
+
+/// Serves the routes for viewing and changing the live `subscription`. They have no
+/// authentication, so `addr` should only be reachable by operators.
+pub async fn start_admin_server(subscription: Arc<Subscription>, addr: SocketAddr) {
+    let app = Router::new()
+        .route("/subscription", get(subscription_handler))
+        .route("/subscription/programs", post(update_programs_handler))
+        .with_state(subscription);
+
+    let listener = TcpListener::bind(addr).await.unwrap();
+    eprintln!("Admin server listening on {}", addr);
+    axum::serve(listener, app).await.unwrap();
+}
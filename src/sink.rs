@@ -0,0 +1,105 @@
+use crate::export::SlotExporter;
+use crate::metrics::Metrics;
+use crate::output::SummaryOutput;
+use crate::summary::SlotSummary;
+use crate::tracker::{EvictionReason, SlotOutcome};
+use std::sync::Arc;
+
+/// Receives slots as their trackers close. Every method defaults to doing
+/// nothing, so a sink only implements the events it cares about.
+pub trait SlotSink: Send + Sync {
+    /// The slot reached Finalized.
+    fn slot_completed(&self, _summary: &SlotSummary) {}
+
+    /// The slot was reported dead.
+    fn slot_dead(&self, _summary: &SlotSummary) {}
+
+    /// The tracker was dropped by the eviction policy before the slot
+    /// finalized or died.
+    fn slot_evicted(&self, _summary: &SlotSummary, _reason: EvictionReason) {}
+}
+
+/// Hands `summary` to the `sink` method matching its outcome.
+pub fn dispatch(sink: &dyn SlotSink, summary: &SlotSummary) {
+    match summary.outcome {
+        SlotOutcome::Finalized => sink.slot_completed(summary),
+        SlotOutcome::Dead => sink.slot_dead(summary),
+        SlotOutcome::Evicted(reason) => sink.slot_evicted(summary, reason),
+    }
+}
+
+/// Several sinks fed in order.
+#[derive(Default, Clone)]
+pub struct SinkSet {
+    sinks: Vec<Arc<dyn SlotSink>>,
+}
+
+impl SinkSet {
+    pub fn push(&mut self, sink: Arc<dyn SlotSink>) {
+        self.sinks.push(sink);
+    }
+}
+
+impl SlotSink for SinkSet {
+    fn slot_completed(&self, summary: &SlotSummary) {
+        for sink in &self.sinks {
+            sink.slot_completed(summary);
+        }
+    }
+
+    fn slot_dead(&self, summary: &SlotSummary) {
+        for sink in &self.sinks {
+            sink.slot_dead(summary);
+        }
+    }
+
+    fn slot_evicted(&self, summary: &SlotSummary, reason: EvictionReason) {
+        for sink in &self.sinks {
+            sink.slot_evicted(summary, reason);
+        }
+    }
+}
+
+// Вывод и экспорт пишут все исходы одинаково, отличаются они полем outcome
+
+impl SlotSink for SummaryOutput {
+    fn slot_completed(&self, summary: &SlotSummary) {
+        self.write(summary);
+    }
+
+    fn slot_dead(&self, summary: &SlotSummary) {
+        self.write(summary);
+    }
+
+    fn slot_evicted(&self, summary: &SlotSummary, _reason: EvictionReason) {
+        self.write(summary);
+    }
+}
+
+impl SlotSink for SlotExporter {
+    fn slot_completed(&self, summary: &SlotSummary) {
+        self.export(summary);
+    }
+
+    fn slot_dead(&self, summary: &SlotSummary) {
+        self.export(summary);
+    }
+
+    fn slot_evicted(&self, summary: &SlotSummary, _reason: EvictionReason) {
+        self.export(summary);
+    }
+}
+
+impl SlotSink for Metrics {
+    fn slot_completed(&self, summary: &SlotSummary) {
+        self.record_slot_summary(summary);
+    }
+
+    fn slot_dead(&self, summary: &SlotSummary) {
+        self.record_slot_summary(summary);
+    }
+
+    fn slot_evicted(&self, summary: &SlotSummary, _reason: EvictionReason) {
+        self.record_slot_summary(summary);
+    }
+}
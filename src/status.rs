@@ -0,0 +1,151 @@
+use crate::tracker::monotonic_time_us;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Те же часы, что и у времени получения сообщений, иначе скачок системных
+// часов навсегда сдвинет возраст и тишину
+fn now_ms() -> u64 {
+    monotonic_time_us(Instant::now()) / 1000
+}
+
+/// Where an endpoint's stream is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamPhase {
+    Connecting,
+    /// Subscribed, no update received yet.
+    Subscribed,
+    Receiving,
+    /// Subscribed, but silent for longer than the readiness limit.
+    Stalled,
+    Disconnected,
+}
+
+/// Snapshot of one endpoint, as served by `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub endpoint: String,
+    pub state: StreamPhase,
+    /// Unix milliseconds of the last state change, on the clock of `monotonic_time_us`.
+    pub state_since_ms: u64,
+    pub last_message_ms: Option<u64>,
+    pub last_message_age_ms: Option<u64>,
+    /// Highest slot seen in a slot or transaction update.
+    pub last_slot: Option<u64>,
+    pub reconnects: u64,
+    pub last_error: Option<String>,
+}
+
+/// Stream state of every endpoint, written by the endpoint tasks and read by
+/// the `/ready` and `/status` handlers.
+#[derive(Debug)]
+pub struct StreamStatus {
+    max_silence_ms: Option<u64>,
+    endpoints: Mutex<Vec<EndpointStatus>>,
+}
+
+impl StreamStatus {
+    /// `max_silence_ms`: a subscribed stream without updates for this long is
+    /// reported as stalled.
+    pub fn new(endpoints: Vec<String>, max_silence_ms: Option<u64>) -> Self {
+        let now = now_ms();
+        let endpoints = endpoints
+            .into_iter()
+            .map(|endpoint| EndpointStatus {
+                endpoint,
+                state: StreamPhase::Connecting,
+                state_since_ms: now,
+                last_message_ms: None,
+                last_message_age_ms: None,
+                last_slot: None,
+                reconnects: 0,
+                last_error: None,
+            })
+            .collect();
+        Self {
+            max_silence_ms,
+            endpoints: Mutex::new(endpoints),
+        }
+    }
+
+    pub fn set_connecting(&self, endpoint: usize) {
+        self.update(endpoint, |status| {
+            if status.state == StreamPhase::Disconnected {
+                status.reconnects += 1;
+            }
+            StreamPhase::Connecting
+        });
+    }
+
+    pub fn set_subscribed(&self, endpoint: usize) {
+        self.update(endpoint, |_| StreamPhase::Subscribed);
+    }
+
+    pub fn set_disconnected(&self, endpoint: usize, error: Option<String>) {
+        self.update(endpoint, |status| {
+            status.last_error = error;
+            StreamPhase::Disconnected
+        });
+    }
+
+    /// Called for every update; `received_at_ms` comes from `monotonic_time_us`
+    /// and `slot` is the slot the update refers to, if any.
+    pub fn record_message(&self, endpoint: usize, received_at_ms: u64, slot: Option<u64>) {
+        self.update(endpoint, |status| {
+            status.last_message_ms = Some(received_at_ms);
+            if let Some(slot) = slot {
+                status.last_slot = Some(status.last_slot.map_or(slot, |s| s.max(slot)));
+            }
+            StreamPhase::Receiving
+        });
+    }
+
+    pub fn snapshot(&self) -> Vec<EndpointStatus> {
+        let now = now_ms();
+        let endpoints = self.endpoints.lock().unwrap();
+        endpoints
+            .iter()
+            .map(|status| {
+                let mut status = status.clone();
+                status.last_message_age_ms =
+                    status.last_message_ms.map(|ts| now.saturating_sub(ts));
+                // Тишину считаем от последнего сообщения или от (пере)подписки
+                let silent_since = status
+                    .last_message_ms
+                    .map_or(status.state_since_ms, |ts| ts.max(status.state_since_ms));
+                if matches!(
+                    status.state,
+                    StreamPhase::Subscribed | StreamPhase::Receiving
+                ) && let Some(max_silence_ms) = self.max_silence_ms
+                    && now.saturating_sub(silent_since) > max_silence_ms
+                {
+                    status.state = StreamPhase::Stalled;
+                }
+                status
+            })
+            .collect()
+    }
+
+    /// Ready when there is at least one endpoint and every endpoint is
+    /// subscribed and not stalled.
+    pub fn is_ready(snapshot: &[EndpointStatus]) -> bool {
+        !snapshot.is_empty()
+            && snapshot.iter().all(|status| {
+                matches!(
+                    status.state,
+                    StreamPhase::Subscribed | StreamPhase::Receiving
+                )
+            })
+    }
+
+    fn update(&self, endpoint: usize, change: impl FnOnce(&mut EndpointStatus) -> StreamPhase) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let status = &mut endpoints[endpoint];
+        let state = change(status);
+        if status.state != state {
+            status.state = state;
+            status.state_since_ms = now_ms();
+        }
+    }
+}
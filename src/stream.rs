@@ -0,0 +1,612 @@
+use crate::accounts::ProgramRegistry;
+use crate::compare::EndpointComparator;
+use crate::config::UpdateKind;
+use crate::metrics::Metrics;
+use crate::recording::Recorder;
+use crate::sink::{SlotSink, dispatch};
+use crate::status::StreamStatus;
+use crate::subscription::Subscription;
+use crate::tls::{TlsOptions, connect_insecure};
+use crate::tracker::{
+    EvictionPolicy, SlotTrackerSet, TrackedUpdate, monotonic_time_us, unix_time_us,
+};
+use anyhow::{Result, anyhow, bail};
+use futures::SinkExt;
+use rand::Rng;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_stream::StreamExt;
+use tonic_health::pb::health_client::HealthClient;
+use yellowstone_grpc_client::{GeyserGrpcClient, GeyserGrpcClientError, InterceptorXToken};
+use yellowstone_grpc_proto::geyser::geyser_client::GeyserClient;
+use yellowstone_grpc_proto::geyser::{
+    SlotStatus, SubscribeRequest, SubscribeRequestPing, SubscribeUpdate, subscribe_update,
+};
+use yellowstone_grpc_proto::prost::Message;
+use yellowstone_grpc_proto::tonic::transport::{Endpoint, Error as TransportError};
+use yellowstone_grpc_proto::tonic::{Code, Status};
+
+// Таймаут на подключение и на каждый unary запрос
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    /// Short label used in logs and metrics.
+    pub name: String,
+    pub endpoint: String,
+    pub x_token: Option<String>,
+    pub tls: TlsOptions,
+}
+
+pub async fn connect(config: &ConnectionConfig) -> Result<GeyserGrpcClient<InterceptorXToken>> {
+    eprintln!(
+        "[{}] Connecting to Yellowstone gRPC endpoint: {}",
+        config.name, config.endpoint
+    );
+
+    // TLS включается по схеме https://
+    let channel = if !config.endpoint.starts_with("https://") {
+        Endpoint::from_shared(config.endpoint.clone())?
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(CONNECT_TIMEOUT)
+            .connect()
+            .await?
+    } else if config.tls.insecure {
+        eprintln!(
+            "[{}] Warning: TLS certificate verification is disabled (--insecure)",
+            config.name
+        );
+        connect_insecure(&config.endpoint, &config.tls, CONNECT_TIMEOUT).await?
+    } else {
+        Endpoint::from_shared(config.endpoint.clone())?
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(CONNECT_TIMEOUT)
+            .tls_config(config.tls.client_tls_config()?)?
+            .connect()
+            .await?
+    };
+
+    let interceptor = InterceptorXToken {
+        x_token: config.x_token.as_deref().map(str::parse).transpose()?,
+        x_request_snapshot: false,
+    };
+    let client = GeyserGrpcClient::new(
+        HealthClient::with_interceptor(channel.clone(), interceptor.clone()),
+        GeyserClient::with_interceptor(channel, interceptor),
+    );
+
+    eprintln!("[{}] Connected successfully!", config.name);
+
+    Ok(client)
+}
+
+/// Exponential reconnect backoff with jitter.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let ceiling = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Give up after this many consecutive failed reconnects (0 = never).
+    pub max_attempts: u32,
+}
+
+/// Pings sent over the subscribe stream and the stall detector.
+#[derive(Debug, Clone)]
+pub struct KeepaliveConfig {
+    /// Send a ping this often; `None` disables client pings.
+    pub ping_interval: Option<Duration>,
+    /// Reconnect when no update other than ping/pong arrives for this long;
+    /// `None` disables the check.
+    pub stall_timeout: Option<Duration>,
+}
+
+/// Bounded queue between the task reading a stream and the task feeding its
+/// updates into the trackers, so slow processing does not delay reads.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueConfig {
+    pub capacity: usize,
+    /// Drop updates while the queue is full instead of waiting for room.
+    pub drop_when_full: bool,
+}
+
+/// Shared state of one endpoint's stream, handed to the per-endpoint task.
+#[derive(Clone)]
+pub struct EndpointContext {
+    pub index: usize,
+    pub name: String,
+    pub metrics: Metrics,
+    pub programs: Arc<ProgramRegistry>,
+    pub eviction: EvictionPolicy,
+    /// Whether slot summaries list the signatures of their transactions.
+    pub summary_signatures: bool,
+    /// Present only when more than one endpoint is being compared.
+    pub comparator: Option<Arc<EndpointComparator>>,
+    pub recorder: Option<Recorder>,
+    /// Present for live streams; feeds `/ready` and `/status`.
+    pub status: Option<Arc<StreamStatus>>,
+    /// Receives the summary of every closed slot.
+    pub sink: Arc<dyn SlotSink>,
+}
+
+/// What the reading task hands to the processing task, in stream order.
+enum StreamEvent {
+    Update {
+        update: Box<SubscribeUpdate>,
+        received_at_us: u64,
+    },
+    /// Programs were enabled or disabled; applies to the updates after it.
+    Programs(Arc<ProgramRegistry>),
+}
+
+/// Keeps one endpoint subscribed, reconnecting with backoff and resuming
+/// from the oldest in-flight slot after every failure. Updates are read in
+/// this task and processed in a separate one, connected by a bounded queue.
+pub async fn run_endpoint(
+    mut ctx: EndpointContext,
+    connection: ConnectionConfig,
+    reconnect: ReconnectConfig,
+    keepalive: KeepaliveConfig,
+    queue: QueueConfig,
+    subscription: Arc<Subscription>,
+) -> Result<()> {
+    let name = ctx.name.clone();
+    let mut slot_trackers = SlotTrackerSet::new(name.clone(), ctx.programs.clone(), ctx.eviction);
+    slot_trackers.expect_block_meta(subscription.subscribes(UpdateKind::BlocksMeta));
+    slot_trackers.include_signatures(ctx.summary_signatures);
+    let mut backoff = Backoff::new(reconnect.initial_backoff, reconnect.max_backoff);
+
+    let (events, events_rx) = mpsc::channel(queue.capacity);
+    let (resume_slot_tx, resume_slot) = watch::channel(None);
+    let processor = tokio::spawn(process_updates(
+        ctx.clone(),
+        slot_trackers,
+        events_rx,
+        resume_slot_tx,
+    ));
+
+    let error = loop {
+        if let Some(status) = &ctx.status {
+            status.set_connecting(ctx.index);
+        }
+        let from_slot = *resume_slot.borrow();
+        let result = run_stream(
+            &mut ctx,
+            &connection,
+            &keepalive,
+            queue,
+            &subscription,
+            &events,
+            from_slot,
+            &mut backoff,
+        )
+        .await;
+        let error = match result {
+            Ok(()) => {
+                eprintln!("[{name}] Stream closed by server");
+                None
+            }
+            Err(e) => {
+                eprintln!("[{name}] Error receiving message: {}", e);
+                ctx.metrics.record_stream_error(&name, error_code(&e));
+                Some(e.to_string())
+            }
+        };
+        if let Some(status) = &ctx.status {
+            status.set_disconnected(ctx.index, error);
+        }
+
+        if reconnect.max_attempts > 0 && backoff.attempt() >= reconnect.max_attempts {
+            break anyhow!(
+                "[{name}] giving up after {} failed reconnect attempts",
+                backoff.attempt()
+            );
+        }
+
+        let delay = backoff.next_delay();
+        eprintln!(
+            "[{name}] Reconnecting in {}ms (attempt {}, from_slot {:?})",
+            delay.as_millis(),
+            backoff.attempt(),
+            *resume_slot.borrow()
+        );
+        tokio::time::sleep(delay).await;
+        ctx.metrics.record_reconnect(&name);
+    };
+
+    // Даем обработчику дочитать очередь, прежде чем выходить
+    drop(events);
+    let _ = processor.await;
+    Err(error)
+}
+
+/// Feeds queued updates into `trackers` and publishes the slot to resume
+/// from after each one.
+async fn process_updates(
+    ctx: EndpointContext,
+    mut trackers: SlotTrackerSet,
+    mut events: mpsc::Receiver<StreamEvent>,
+    resume_slot: watch::Sender<Option<u64>>,
+) {
+    while let Some(event) = events.recv().await {
+        ctx.metrics.set_queue_depth(&ctx.name, events.len());
+        match event {
+            StreamEvent::Programs(programs) => trackers.set_programs(programs),
+            StreamEvent::Update {
+                update,
+                received_at_us,
+            } => {
+                let waited_us =
+                    monotonic_time_us(std::time::Instant::now()).saturating_sub(received_at_us);
+                ctx.metrics
+                    .record_queue_delay(&ctx.name, waited_us as f64 / 1000.0);
+                // Размер и копия для записи стоят O(размер сообщения), поэтому не в задаче чтения
+                if let Some(oneof) = &update.update_oneof {
+                    ctx.metrics.record_stream_message(
+                        &ctx.name,
+                        update_type(oneof),
+                        update.encoded_len(),
+                    );
+                }
+                if let Err(e) = apply_update(&ctx, &mut trackers, &update, received_at_us) {
+                    eprintln!("[{}] Failed to process update: {:#}", ctx.name, e);
+                }
+                if let Some(recorder) = &ctx.recorder {
+                    recorder.record(&ctx.name, received_at_us, *update);
+                }
+            }
+        }
+        resume_slot.send_replace(trackers.resume_slot());
+    }
+}
+
+/// Queues an update for processing. A full queue either blocks reading or,
+/// with `drop_when_full`, loses the update; both are counted.
+async fn enqueue(
+    ctx: &EndpointContext,
+    queue: QueueConfig,
+    events: &mpsc::Sender<StreamEvent>,
+    event: StreamEvent,
+) -> Result<()> {
+    match events.try_send(event) {
+        Ok(()) => {}
+        Err(TrySendError::Full(event)) => {
+            if queue.drop_when_full {
+                ctx.metrics.record_queue_dropped(&ctx.name);
+            } else {
+                ctx.metrics.record_queue_blocked(&ctx.name);
+                events
+                    .send(event)
+                    .await
+                    .map_err(|_| anyhow!("update processing stopped"))?;
+            }
+        }
+        Err(TrySendError::Closed(_)) => bail!("update processing stopped"),
+    }
+    ctx.metrics
+        .set_queue_depth(&ctx.name, events.max_capacity() - events.capacity());
+    Ok(())
+}
+
+/// Connects, subscribes and queues updates for processing until the stream
+/// ends, fails or stalls. The backoff is reset once the first update arrives.
+/// Program changes published by `subscription` are sent over the open stream.
+#[allow(clippy::too_many_arguments)]
+async fn run_stream(
+    ctx: &mut EndpointContext,
+    config: &ConnectionConfig,
+    keepalive: &KeepaliveConfig,
+    queue: QueueConfig,
+    subscription: &Subscription,
+    events: &mpsc::Sender<StreamEvent>,
+    from_slot: Option<u64>,
+    backoff: &mut Backoff,
+) -> Result<()> {
+    let mut programs_rx = subscription.watch_programs();
+    ctx.programs = programs_rx.borrow_and_update().clone();
+    events
+        .send(StreamEvent::Programs(ctx.programs.clone()))
+        .await?;
+    let request = subscription.request(&ctx.programs, from_slot);
+
+    let mut client = connect(config).await?;
+    let (mut sink, mut stream) = match client.subscribe_with_request(Some(request.clone())).await {
+        Err(GeyserGrpcClientError::TonicStatus(status))
+            if status.code() == Code::InvalidArgument && request.from_slot.is_some() =>
+        {
+            // Провайдер не хранит историю с нужного слота - подписываемся без from_slot
+            eprintln!(
+                "from_slot {:?} rejected ({}), resubscribing from the tip",
+                request.from_slot,
+                status.message()
+            );
+            client
+                .subscribe_with_request(Some(SubscribeRequest {
+                    from_slot: None,
+                    ..request
+                }))
+                .await?
+        }
+        result => result?,
+    };
+
+    eprintln!("[{}] Listening for updates...", config.name);
+    if let Some(status) = &ctx.status {
+        status.set_subscribed(ctx.index);
+    }
+
+    let mut ping_timer = keepalive.ping_interval.map(|interval| {
+        let mut timer = tokio::time::interval_at(Instant::now() + interval, interval);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        timer
+    });
+    let mut next_ping_id: i32 = 1;
+    // Пинг, на который еще не пришел pong: id и время отправки
+    let mut pending_ping: Option<(i32, Instant)> = None;
+    let mut last_update_at = Instant::now();
+    // Интервалы считаем только внутри одного стрима, без паузы на переподключение
+    let mut last_message_at: Option<Instant> = None;
+
+    loop {
+        let stall_deadline = keepalive
+            .stall_timeout
+            .map(|timeout| last_update_at + timeout);
+
+        let message = tokio::select! {
+            message = stream.next() => message,
+            _ = tick(&mut ping_timer) => {
+                let id = next_ping_id;
+                next_ping_id = next_ping_id.checked_add(1).unwrap_or(1);
+                pending_ping = Some((id, Instant::now()));
+                sink.send(ping_request(id)).await?;
+                continue;
+            }
+            changed = programs_rx.changed() => {
+                changed?;
+                ctx.programs = programs_rx.borrow_and_update().clone();
+                events.send(StreamEvent::Programs(ctx.programs.clone())).await?;
+                sink.send(subscription.request(&ctx.programs, None)).await?;
+                eprintln!(
+                    "[{}] Subscription updated, watching {} programs",
+                    ctx.name,
+                    ctx.programs.enabled().count()
+                );
+                continue;
+            }
+            _ = sleep_until_deadline(stall_deadline) => {
+                ctx.metrics.record_stream_stall(&ctx.name);
+                bail!(
+                    "no updates for {}ms, stream stalled",
+                    keepalive.stall_timeout.unwrap_or_default().as_millis()
+                );
+            }
+        };
+        let Some(message) = message else {
+            break;
+        };
+
+        let msg = message?;
+        // Время получения фиксируем сразу, до любой обработки
+        let received_at = Instant::now();
+        let received_at_us = monotonic_time_us(received_at.into_std());
+        // Задержку доставки считаем по системным часам, как и серверный created_at
+        record_transport_latency(ctx, &msg, unix_time_us());
+        backoff.reset();
+
+        if let Some(previous) = last_message_at.replace(received_at) {
+            let interval = received_at.duration_since(previous);
+            ctx.metrics
+                .record_message_interval(&ctx.name, interval.as_secs_f64() * 1000.0);
+        }
+        if let Some(subscribe_update::UpdateOneof::Slot(slot)) = &msg.update_oneof
+            && let Ok(status) = SlotStatus::try_from(slot.status)
+        {
+            ctx.metrics.record_slot_seen(&ctx.name, slot.slot, status);
+        }
+
+        match &msg.update_oneof {
+            Some(subscribe_update::UpdateOneof::Ping(_)) => {
+                // Сервер проверяет, что клиент жив; без ответа часть провайдеров рвет стрим
+                sink.send(ping_request(0)).await?;
+            }
+            Some(subscribe_update::UpdateOneof::Pong(pong)) => {
+                if let Some((id, sent_at)) = pending_ping
+                    && id == pong.id
+                {
+                    let rtt = received_at.duration_since(sent_at);
+                    ctx.metrics
+                        .record_ping_rtt(&ctx.name, rtt.as_secs_f64() * 1000.0);
+                    pending_ping = None;
+                }
+            }
+            update => {
+                last_update_at = received_at;
+                if let Some(status) = &ctx.status {
+                    status.record_message(
+                        ctx.index,
+                        received_at_us / 1000,
+                        update.as_ref().and_then(update_slot),
+                    );
+                }
+            }
+        }
+
+        let event = StreamEvent::Update {
+            update: Box::new(msg),
+            received_at_us,
+        };
+        enqueue(ctx, queue, events, event).await?;
+    }
+
+    Ok(())
+}
+
+fn ping_request(id: i32) -> SubscribeRequest {
+    SubscribeRequest {
+        ping: Some(SubscribeRequestPing { id }),
+        ..Default::default()
+    }
+}
+
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Records how long `msg` took from the server's `created_at` to
+/// `received_unix_us`. Both are wall-clock times, so a clock step shifts only
+/// the updates received around it.
+pub fn record_transport_latency(
+    ctx: &EndpointContext,
+    msg: &SubscribeUpdate,
+    received_unix_us: u64,
+) {
+    if let (Some(created_at), Some(update)) = (&msg.created_at, &msg.update_oneof) {
+        let created_at_us = created_at.seconds * 1_000_000 + created_at.nanos as i64 / 1000;
+        let latency_ms = (received_unix_us as i64 - created_at_us) as f64 / 1000.0;
+        ctx.metrics
+            .record_transport_latency(update_type(update), &ctx.name, latency_ms);
+    }
+}
+
+/// Feeds one update into the trackers, metrics and endpoint comparison, and
+/// hands the summaries of the slots it closed to the sink.
+pub fn apply_update(
+    ctx: &EndpointContext,
+    trackers: &mut SlotTrackerSet,
+    msg: &SubscribeUpdate,
+    received_at_us: u64,
+) -> Result<()> {
+    let metrics = &ctx.metrics;
+    let applied = trackers.apply_update(msg, received_at_us)?;
+    metrics.set_live_slot_trackers(&ctx.name, trackers.in_flight());
+    match applied {
+        Some(TrackedUpdate::SlotStatus { slot, status }) => {
+            if let Some(comparator) = &ctx.comparator {
+                comparator.record_slot_status(ctx.index, slot, status, received_at_us, metrics);
+            }
+        }
+        Some(TrackedUpdate::Transaction { slot, signature }) => {
+            if let Some(comparator) = &ctx.comparator {
+                comparator.record_transaction(ctx.index, slot, signature, received_at_us, metrics);
+            }
+        }
+        Some(TrackedUpdate::BlockMeta { .. }) | None => {}
+    }
+
+    for summary in trackers.drain_summaries() {
+        dispatch(ctx.sink.as_ref(), &summary);
+    }
+
+    Ok(())
+}
+
+/// gRPC status code of a failed connection or stream, as a metric label.
+/// Failures that carry no status are reported as `transport` or `other`.
+fn error_code(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        let status = match cause.downcast_ref::<GeyserGrpcClientError>() {
+            Some(GeyserGrpcClientError::TonicStatus(status)) => Some(status),
+            _ => cause.downcast_ref::<Status>(),
+        };
+        if let Some(status) = status {
+            return code_label(status.code());
+        }
+        if cause.is::<TransportError>() {
+            return "transport";
+        }
+    }
+    "other"
+}
+
+fn code_label(code: Code) -> &'static str {
+    match code {
+        Code::Ok => "ok",
+        Code::Cancelled => "cancelled",
+        Code::Unknown => "unknown",
+        Code::InvalidArgument => "invalid_argument",
+        Code::DeadlineExceeded => "deadline_exceeded",
+        Code::NotFound => "not_found",
+        Code::AlreadyExists => "already_exists",
+        Code::PermissionDenied => "permission_denied",
+        Code::ResourceExhausted => "resource_exhausted",
+        Code::FailedPrecondition => "failed_precondition",
+        Code::Aborted => "aborted",
+        Code::OutOfRange => "out_of_range",
+        Code::Unimplemented => "unimplemented",
+        Code::Internal => "internal",
+        Code::Unavailable => "unavailable",
+        Code::DataLoss => "data_loss",
+        Code::Unauthenticated => "unauthenticated",
+    }
+}
+
+/// Short name of an update kind, used as a metric label.
+pub fn update_type(update: &subscribe_update::UpdateOneof) -> &'static str {
+    match update {
+        subscribe_update::UpdateOneof::Account(_) => "account",
+        subscribe_update::UpdateOneof::Slot(_) => "slot",
+        subscribe_update::UpdateOneof::Transaction(_) => "transaction",
+        subscribe_update::UpdateOneof::TransactionStatus(_) => "transaction_status",
+        subscribe_update::UpdateOneof::Block(_) => "block",
+        subscribe_update::UpdateOneof::Ping(_) => "ping",
+        subscribe_update::UpdateOneof::Pong(_) => "pong",
+        subscribe_update::UpdateOneof::BlockMeta(_) => "block_meta",
+        subscribe_update::UpdateOneof::Entry(_) => "entry",
+    }
+}
+
+/// Slot an update belongs to, for slot and transaction updates.
+fn update_slot(update: &subscribe_update::UpdateOneof) -> Option<u64> {
+    match update {
+        subscribe_update::UpdateOneof::Slot(slot) => Some(slot.slot),
+        subscribe_update::UpdateOneof::Transaction(transaction) => Some(transaction.slot),
+        _ => None,
+    }
+}
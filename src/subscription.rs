@@ -0,0 +1,86 @@
+use crate::accounts::{Program, ProgramRegistry};
+use crate::config::{SubscriptionConfig, UpdateKind};
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use yellowstone_grpc_proto::geyser::SubscribeRequest;
+
+/// The subscription all endpoints follow. Program changes made at runtime
+/// are published to the endpoint tasks, which resend their `SubscribeRequest`
+/// over the live stream.
+pub struct Subscription {
+    config: SubscriptionConfig,
+    programs: watch::Sender<Arc<ProgramRegistry>>,
+    // Изменения реестра делаем по одному, чтобы не потерять параллельные
+    update_lock: Mutex<()>,
+}
+
+/// What `GET /subscription` reports.
+#[derive(Debug, Serialize)]
+pub struct SubscriptionView<'a> {
+    pub programs: Vec<&'a Program>,
+    #[serde(flatten)]
+    pub config: &'a SubscriptionConfig,
+}
+
+impl Subscription {
+    pub fn new(config: SubscriptionConfig, programs: Arc<ProgramRegistry>) -> Self {
+        Self {
+            config,
+            programs: watch::Sender::new(programs),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn programs(&self) -> Arc<ProgramRegistry> {
+        self.programs.borrow().clone()
+    }
+
+    /// Receives every new program registry; the current one is marked as seen.
+    pub fn watch_programs(&self) -> watch::Receiver<Arc<ProgramRegistry>> {
+        self.programs.subscribe()
+    }
+
+    pub fn subscribes(&self, kind: UpdateKind) -> bool {
+        self.config.subscribes(kind)
+    }
+
+    /// Request for `programs`, normally the latest value from `watch_programs`.
+    pub fn request(&self, programs: &ProgramRegistry, from_slot: Option<u64>) -> SubscribeRequest {
+        self.config.request(&programs.enabled_ids(), from_slot)
+    }
+
+    /// Adds new programs to the registry, enables and disables programs by id,
+    /// code or name, then publishes the new registry. Nothing changes if a new
+    /// program is invalid, any key is unknown or no program would be left enabled.
+    pub fn update_programs(
+        &self,
+        add: Vec<Program>,
+        enable: &[String],
+        disable: &[String],
+    ) -> Result<Arc<ProgramRegistry>> {
+        let _guard = self.update_lock.lock().unwrap();
+        let mut programs = self.programs().with_added(add)?;
+        for key in enable {
+            programs.set_enabled(key, true)?;
+        }
+        for key in disable {
+            programs.set_enabled(key, false)?;
+        }
+        if programs.enabled().next().is_none() {
+            bail!("at least one program must stay enabled");
+        }
+
+        let programs = Arc::new(programs);
+        self.programs.send_replace(programs.clone());
+        Ok(programs)
+    }
+
+    pub fn view<'a>(&'a self, programs: &'a ProgramRegistry) -> SubscriptionView<'a> {
+        SubscriptionView {
+            programs: programs.enabled().collect(),
+            config: &self.config,
+        }
+    }
+}
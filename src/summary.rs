@@ -0,0 +1,193 @@
+use crate::metrics::{NO_STATUS_YET, status_label};
+use crate::tracker::{EvictionReason, SlotOutcome};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+/// Everything known about a slot when its tracker is closed. Timestamps are
+/// local receive times in Unix microseconds, taken from a monotonic clock
+/// anchored to the wall clock (see `tracker::monotonic_time_us`).
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotSummary {
+    pub outcome: SlotOutcome,
+    pub eviction_reason: Option<EvictionReason>,
+    pub endpoint: String,
+    pub slot: u64,
+    /// Update that created the tracker: `transaction`, `block_meta` or
+    /// `slot_update_<status>`.
+    pub creator: String,
+    pub created_at_us: u64,
+    pub first_tx_at_us: Option<u64>,
+    pub last_tx_at_us: Option<u64>,
+    pub duration_us: u64,
+    pub total_txs: u64,
+    /// Transactions delivered again with a signature already counted.
+    pub duplicate_txs: u64,
+    pub tx_index: Option<TxIndexSummary>,
+    pub block_meta: Option<BlockMetaSummary>,
+    /// The slot finalized without block meta despite a `blocks_meta` subscription.
+    pub block_meta_missing: bool,
+    /// Transactions by the slot status they arrived in; `None` counts those
+    /// that arrived before the first status.
+    #[serde(serialize_with = "serialize_tx_by_status")]
+    pub tx_by_status: Vec<(Option<SlotStatus>, u64)>,
+    pub tx_by_program: Vec<ProgramSummary>,
+    pub timeline: Vec<StatusTimestamp>,
+    /// Base58 signatures of the counted transactions, sorted; collected only
+    /// when enabled with `SlotTrackerSet::include_signatures`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<Vec<String>>,
+}
+
+/// Transactions of one registry program within a slot.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramSummary {
+    pub program: String,
+    pub code: String,
+    pub tx_count: u64,
+    pub first_tx_at_us: u64,
+    pub last_tx_at_us: u64,
+}
+
+/// Block positions of a slot's transactions compared with their arrival order.
+#[derive(Debug, Clone, Serialize)]
+pub struct TxIndexSummary {
+    pub min_index: u64,
+    pub max_index: u64,
+    /// Transactions that arrived after one with a higher index.
+    pub out_of_order_txs: u64,
+    /// Indices between `min_index` and `max_index` that never arrived; with
+    /// account filters most of them belong to other programs' transactions.
+    pub missing_indices: u64,
+    /// Arrival of the highest index minus arrival of the lowest one; negative
+    /// when they were delivered in reverse.
+    pub first_to_last_index_us: i64,
+}
+
+/// Block meta of the slot next to what the stream delivered of the block.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockMetaSummary {
+    pub blockhash: String,
+    pub block_time: Option<i64>,
+    pub block_height: Option<u64>,
+    pub executed_transaction_count: u64,
+    /// Executed transactions that were not delivered. Only those matching
+    /// `tx_filters` could have been, so with account or vote filters most of
+    /// them are outside the subscription rather than lost.
+    pub unobserved_txs: u64,
+    /// Subscription filters the delivered transactions matched.
+    pub tx_filters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusTimestamp {
+    #[serde(serialize_with = "serialize_status")]
+    pub status: SlotStatus,
+    pub received_at_us: u64,
+}
+
+impl SlotSummary {
+    /// When `status` was received for this slot, if it was.
+    pub fn status_ts(&self, status: SlotStatus) -> Option<u64> {
+        self.timeline
+            .iter()
+            .find(|entry| entry.status == status)
+            .map(|entry| entry.received_at_us)
+    }
+
+    /// The `SLOT_FINALIZED slot:... endpoint:...` line of the text format.
+    pub fn text_line(&self) -> String {
+        let status_counts = self
+            .tx_by_status
+            .iter()
+            .map(|(status, count)| match status {
+                Some(status) => format!("{:?}:{}", status, count),
+                None => format!("no_status_yet:{}", count),
+            })
+            .collect::<Vec<_>>();
+
+        let program_counts = self
+            .tx_by_program
+            .iter()
+            .map(|program| format!("{}:{}", program.code, program.tx_count))
+            .collect::<Vec<_>>();
+
+        // Смещения статусов относительно создания трекера
+        let timeline = self
+            .timeline
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{:?}:+{:.3}",
+                    entry.status,
+                    us_to_ms(entry.received_at_us.saturating_sub(self.created_at_us))
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let tx_index = self
+            .tx_index
+            .as_ref()
+            .map(|index| {
+                format!(
+                    "{}-{} out_of_order:{} missing:{} first_to_last:{:+.3}ms",
+                    index.min_index,
+                    index.max_index,
+                    index.out_of_order_txs,
+                    index.missing_indices,
+                    index.first_to_last_index_us as f64 / 1000.0
+                )
+            })
+            .unwrap_or_default();
+
+        let block_meta = match &self.block_meta {
+            Some(meta) => format!(
+                "height:{} executed_txs:{} unobserved_txs:{} filters:{}",
+                meta.block_height
+                    .map_or_else(|| "-".to_string(), |height| height.to_string()),
+                meta.executed_transaction_count,
+                meta.unobserved_txs,
+                meta.tx_filters.join(",")
+            ),
+            None if self.block_meta_missing => "missing".to_string(),
+            None => String::new(),
+        };
+
+        format!(
+            "{} slot:{} creator:{} duration:{:.3}ms total_txs:{} duplicate_txs:{} tx_by_status:[{}] tx_by_program:[{}] timeline:[{}] tx_index:[{}] block_meta:[{}] endpoint:{}",
+            self.outcome.as_str(),
+            self.slot,
+            self.creator,
+            us_to_ms(self.duration_us),
+            self.total_txs,
+            self.duplicate_txs,
+            status_counts.join(" "),
+            program_counts.join(" "),
+            timeline.join(" "),
+            tx_index,
+            block_meta,
+            self.endpoint
+        )
+    }
+}
+
+/// Microseconds as fractional milliseconds, the unit of text output and histograms.
+pub fn us_to_ms(us: u64) -> f64 {
+    us as f64 / 1000.0
+}
+
+fn serialize_status<S: Serializer>(status: &SlotStatus, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(status_label(*status))
+}
+
+// Счетчики по статусам пишем объектом {"no_status_yet": 1, "processed": 2}
+fn serialize_tx_by_status<S: Serializer>(
+    counts: &[(Option<SlotStatus>, u64)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(counts.len()))?;
+    for (status, count) in counts {
+        map.serialize_entry(status.map_or(NO_STATUS_YET, status_label), count)?;
+    }
+    map.end()
+}
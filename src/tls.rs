@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, bail};
+use hyper_util::rt::TokioIo;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use yellowstone_grpc_client::ClientTlsConfig;
+use yellowstone_grpc_proto::tonic::codegen::http::Uri;
+use yellowstone_grpc_proto::tonic::transport::{Certificate, Channel, Endpoint, Identity};
+
+/// TLS settings applied to every `https://` endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// PEM bundle trusted in addition to the system roots.
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate chain for mutual TLS; needs `client_key`.
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    /// Name to send as SNI and to check the server certificate against,
+    /// instead of the endpoint's host.
+    pub domain: Option<String>,
+    /// Accept any server certificate.
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    /// Whether any option was set, i.e. the user expects TLS to be used.
+    pub fn is_set(&self) -> bool {
+        self.ca_cert.is_some()
+            || self.client_cert.is_some()
+            || self.client_key.is_some()
+            || self.domain.is_some()
+            || self.insecure
+    }
+
+    /// Config for tonic's own TLS stack, used when certificates are verified.
+    pub fn client_tls_config(&self) -> Result<ClientTlsConfig> {
+        let mut tls = ClientTlsConfig::new().with_enabled_roots();
+        if let Some(path) = &self.ca_cert {
+            tls = tls.ca_certificate(Certificate::from_pem(read(path)?));
+        }
+        if let Some((cert, key)) = self.client_identity_paths()? {
+            tls = tls.identity(Identity::from_pem(read(cert)?, read(key)?));
+        }
+        if let Some(domain) = &self.domain {
+            tls = tls.domain_name(domain.clone());
+        }
+        Ok(tls)
+    }
+
+    /// rustls config with a verifier that accepts any server certificate.
+    /// Handshake signatures are still checked, so the server has to own the
+    /// key of the certificate it presents.
+    fn insecure_client_config(&self) -> Result<ClientConfig> {
+        let provider = CryptoProvider::get_default()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyServerCert(provider)));
+
+        let mut config = match self.client_identity_paths()? {
+            Some((cert, key)) => {
+                let certs = CertificateDer::pem_file_iter(cert)
+                    .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                    .with_context(|| format!("failed to read {}", cert.display()))?;
+                let key = PrivateKeyDer::from_pem_file(key)
+                    .with_context(|| format!("failed to read {}", key.display()))?;
+                builder.with_client_auth_cert(certs, key)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+
+    fn client_identity_paths(&self) -> Result<Option<(&Path, &Path)>> {
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Ok(Some((cert, key))),
+            (None, None) => Ok(None),
+            _ => bail!("client certificate and key must be given together"),
+        }
+    }
+}
+
+/// Connects to `url` over TLS without verifying the server certificate.
+///
+/// tonic's TLS stack has no hook for a custom verifier, so the handshake is
+/// done in our own connector and tonic sees a plain `http://` endpoint whose
+/// origin is the original `https://` URL.
+pub async fn connect_insecure(
+    url: &str,
+    options: &TlsOptions,
+    timeout: Duration,
+) -> Result<Channel> {
+    let uri: Uri = url
+        .parse()
+        .with_context(|| format!("invalid endpoint {}", url))?;
+    let host = uri
+        .host()
+        .context("endpoint has no host")?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(443);
+    let server_name = ServerName::try_from(options.domain.clone().unwrap_or_else(|| host.clone()))
+        .context("invalid TLS server name")?;
+    let connector = TlsConnector::from(Arc::new(options.insecure_client_config()?));
+
+    let endpoint = Endpoint::from_shared(format!("http://{}:{}", uri.host().unwrap(), port))?
+        .origin(uri)
+        .connect_timeout(timeout)
+        .timeout(timeout);
+    let channel = endpoint
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            let connector = connector.clone();
+            let server_name = server_name.clone();
+            let host = host.clone();
+            async move {
+                let tcp = TcpStream::connect((host.as_str(), port)).await?;
+                tcp.set_nodelay(true)?;
+                let tls = connector.connect(server_name, tcp).await?;
+                Ok::<_, std::io::Error>(TokioIo::new(tls))
+            }
+        }))
+        .await?;
+    Ok(channel)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+#[derive(Debug)]
+struct AcceptAnyServerCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyServerCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
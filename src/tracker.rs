@@ -0,0 +1,626 @@
+use crate::accounts::ProgramRegistry;
+use crate::summary::{
+    BlockMetaSummary, ProgramSummary, SlotSummary, StatusTimestamp, TxIndexSummary,
+};
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use yellowstone_grpc_proto::geyser::{
+    SlotStatus, SubscribeUpdate, SubscribeUpdateTransactionInfo, subscribe_update::UpdateOneof,
+};
+
+// Сколько завершенных слотов помним, чтобы отбрасывать повторы после переподключения
+const COMPLETED_SLOTS_RETAINED: usize = 4096;
+
+/// Local wall-clock time in microseconds since the Unix epoch.
+pub fn unix_time_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros() as u64
+}
+
+/// `at` on a monotonic clock, in microseconds. The clock is anchored to the
+/// wall clock once, on first use, so values read as Unix time while the
+/// difference between two of them is immune to NTP steps.
+pub fn monotonic_time_us(at: Instant) -> u64 {
+    static ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+    let &(anchor, anchor_us) = ANCHOR.get_or_init(|| (Instant::now(), unix_time_us()));
+    match at.checked_duration_since(anchor) {
+        Some(elapsed) => anchor_us + elapsed.as_micros() as u64,
+        None => anchor_us.saturating_sub(anchor.duration_since(at).as_micros() as u64),
+    }
+}
+
+/// How a slot tracker was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotOutcome {
+    Finalized,
+    Dead,
+    /// Dropped by the eviction policy before reaching Finalized or Dead.
+    Evicted(EvictionReason),
+}
+
+impl SlotOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlotOutcome::Finalized => "SLOT_FINALIZED",
+            SlotOutcome::Dead => "SLOT_DEAD",
+            SlotOutcome::Evicted(_) => "SLOT_EVICTED",
+        }
+    }
+
+    /// Lowercase name without the eviction reason, used in structured output.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SlotOutcome::Finalized => "finalized",
+            SlotOutcome::Dead => "dead",
+            SlotOutcome::Evicted(_) => "evicted",
+        }
+    }
+}
+
+// В JSON исход пишем коротко, причина вытеснения идет отдельным полем
+impl Serialize for SlotOutcome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionReason {
+    /// Tracker lived longer than `EvictionPolicy::max_age_ms`.
+    Age,
+    /// Slot fell more than `EvictionPolicy::max_slot_distance` behind the highest seen slot.
+    Distance,
+}
+
+impl EvictionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionReason::Age => "age",
+            EvictionReason::Distance => "distance",
+        }
+    }
+}
+
+/// Limits after which a tracker that never got Finalized or Dead is flushed.
+/// `None` disables the corresponding check.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EvictionPolicy {
+    pub max_age_ms: Option<u64>,
+    pub max_slot_distance: Option<u64>,
+}
+
+/// Block meta of a slot, from the optional `blocks_meta` subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockMeta {
+    pub blockhash: String,
+    pub block_time: Option<i64>,
+    pub block_height: Option<u64>,
+    pub executed_transaction_count: u64,
+}
+
+/// Transactions of one registry program within a slot.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramActivity {
+    pub tx_count: u64,
+    pub first_tx_ts: u64,
+    pub last_tx_ts: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotTracker {
+    slot: u64,
+    creator: String,
+    create_ts: u64,
+    first_tx_ts: Option<u64>,
+    last_tx_ts: Option<u64>,
+    current_status: Option<SlotStatus>,
+    // Все статусы слота с временем получения, в порядке прихода
+    // (повторы после from_slot пропускаем)
+    status_timeline: Vec<(SlotStatus, u64)>,
+    // Base58 сигнатуры учтенных транзакций и число их повторов
+    signatures: BTreeSet<String>,
+    duplicate_txs: u64,
+    // Индексы транзакций в блоке в порядке прихода, с временем получения
+    tx_indices: Vec<(u64, u64)>,
+    // Фильтры подписки, по которым пришли транзакции слота
+    tx_filters: BTreeSet<String>,
+    block_meta: Option<BlockMeta>,
+    // Счетчики транзакций по статусам (используем SlotStatus как ключ)
+    tx_counts: HashMap<SlotStatus, u64>,
+    // Отдельный счетчик транзакций для трекеров, созданных транзакциями
+    tx_initiated_count: u64,
+    // Активность по программам из реестра (ключ - индекс в ProgramRegistry)
+    programs: BTreeMap<usize, ProgramActivity>,
+}
+
+impl SlotTracker {
+    /// `now` is the receive time (microseconds, see `monotonic_time_us`) of the
+    /// update that created the tracker.
+    pub fn new(slot: u64, creator: String, now: u64) -> Self {
+        // println!("Create slot tracker");
+        Self {
+            slot,
+            creator,
+            create_ts: now,
+            first_tx_ts: None,
+            last_tx_ts: None,
+            current_status: None,
+            status_timeline: Vec::new(),
+            signatures: BTreeSet::new(),
+            duplicate_txs: 0,
+            tx_indices: Vec::new(),
+            tx_filters: BTreeSet::new(),
+            block_meta: None,
+            tx_counts: HashMap::new(),
+            tx_initiated_count: 0,
+            programs: BTreeMap::new(),
+        }
+    }
+
+    /// Returns `false` if a transaction with this signature was already counted;
+    /// such duplicates are only counted in `duplicate_txs`.
+    /// `index` is the transaction's position in the block, `programs` the
+    /// registry indices of the programs it touched.
+    pub fn apply_transaction(
+        &mut self,
+        signature: &[u8],
+        index: u64,
+        programs: &[usize],
+        now: u64,
+    ) -> bool {
+        // Повторы приходят после переподключения с from_slot или по нескольким фильтрам
+        if !self
+            .signatures
+            .insert(bs58::encode(signature).into_string())
+        {
+            self.duplicate_txs += 1;
+            return false;
+        }
+
+        // Устанавливаем время первой транзакции, если это первая
+        if self.first_tx_ts.is_none() {
+            self.first_tx_ts = Some(now);
+        }
+
+        self.last_tx_ts = Some(now);
+        self.tx_indices.push((index, now));
+
+        match self.current_status {
+            None => self.tx_initiated_count += 1,
+            Some(current_status) => {
+                *self.tx_counts.entry(current_status).or_insert(0) += 1;
+            }
+        }
+
+        for &program in programs {
+            let activity = self.programs.entry(program).or_insert(ProgramActivity {
+                tx_count: 0,
+                first_tx_ts: now,
+                last_tx_ts: now,
+            });
+            activity.tx_count += 1;
+            activity.last_tx_ts = now;
+        }
+
+        true
+    }
+
+    /// Returns `false` if this status was already applied to the slot.
+    pub fn update_status(&mut self, new_status: SlotStatus, now: u64) -> bool {
+        if self.status_ts(new_status).is_some() {
+            return false;
+        }
+        self.status_timeline.push((new_status, now));
+        self.current_status = Some(new_status);
+        true
+    }
+
+    /// When `status` was received for this slot, if it was.
+    pub fn status_ts(&self, status: SlotStatus) -> Option<u64> {
+        self.status_timeline
+            .iter()
+            .find(|(s, _)| *s == status)
+            .map(|(_, ts)| *ts)
+    }
+
+    /// Remembers the subscription filters a transaction of this slot matched.
+    pub fn add_tx_filters(&mut self, filters: &[String]) {
+        for filter in filters {
+            self.tx_filters.insert(filter.clone());
+        }
+    }
+
+    /// Returns `false` if the slot already has its block meta.
+    pub fn set_block_meta(&mut self, meta: BlockMeta) -> bool {
+        if self.block_meta.is_some() {
+            return false;
+        }
+        self.block_meta = Some(meta);
+        true
+    }
+
+    /// Compares the block indices of the counted transactions with the order
+    /// they arrived in; `None` before the first transaction.
+    fn tx_index_summary(&self) -> Option<TxIndexSummary> {
+        let &(min_index, min_index_ts) = self.tx_indices.iter().min_by_key(|(index, _)| *index)?;
+        let &(max_index, max_index_ts) = self.tx_indices.iter().max_by_key(|(index, _)| *index)?;
+
+        // Транзакция пришла не по порядку, если до нее уже была транзакция с большим индексом
+        let mut out_of_order_txs = 0;
+        let mut highest = None;
+        for &(index, _) in &self.tx_indices {
+            if highest.is_some_and(|highest| index < highest) {
+                out_of_order_txs += 1;
+            }
+            highest = highest.max(Some(index));
+        }
+
+        Some(TxIndexSummary {
+            min_index,
+            max_index,
+            out_of_order_txs,
+            missing_indices: (max_index - min_index + 1)
+                .saturating_sub(self.tx_indices.len() as u64),
+            first_to_last_index_us: max_index_ts as i64 - min_index_ts as i64,
+        })
+    }
+
+    pub fn summarize(
+        &self,
+        outcome: SlotOutcome,
+        endpoint: &str,
+        registry: &ProgramRegistry,
+    ) -> SlotSummary {
+        // Время монотонное, но записи для реплея могли сделать и на прыгающих часах
+        let duration_us = match (self.first_tx_ts, self.last_tx_ts) {
+            (Some(first), Some(last)) => last.saturating_sub(first),
+            _ => 0,
+        };
+
+        let total_txs = self.tx_counts.values().sum::<u64>() + self.tx_initiated_count;
+
+        // Счетчики по статусам в порядке прихода статусов
+        let mut tx_by_status = Vec::new();
+        if self.tx_initiated_count > 0 {
+            tx_by_status.push((None, self.tx_initiated_count));
+        }
+        for (status, _) in &self.status_timeline {
+            if let Some(&count) = self.tx_counts.get(status)
+                && count > 0
+            {
+                tx_by_status.push((Some(*status), count));
+            }
+        }
+
+        let tx_by_program = self
+            .programs
+            .iter()
+            .map(|(&index, activity)| {
+                let program = registry.get(index);
+                ProgramSummary {
+                    program: program.name.clone(),
+                    code: program.code.clone(),
+                    tx_count: activity.tx_count,
+                    first_tx_at_us: activity.first_tx_ts,
+                    last_tx_at_us: activity.last_tx_ts,
+                }
+            })
+            .collect();
+
+        let timeline = self
+            .status_timeline
+            .iter()
+            .map(|&(status, received_at_us)| StatusTimestamp {
+                status,
+                received_at_us,
+            })
+            .collect();
+
+        SlotSummary {
+            outcome,
+            eviction_reason: match outcome {
+                SlotOutcome::Evicted(reason) => Some(reason),
+                _ => None,
+            },
+            endpoint: endpoint.to_string(),
+            slot: self.slot,
+            creator: self.creator.clone(),
+            created_at_us: self.create_ts,
+            first_tx_at_us: self.first_tx_ts,
+            last_tx_at_us: self.last_tx_ts,
+            duration_us,
+            total_txs,
+            duplicate_txs: self.duplicate_txs,
+            tx_index: self.tx_index_summary(),
+            block_meta: self.block_meta.as_ref().map(|meta| BlockMetaSummary {
+                blockhash: meta.blockhash.clone(),
+                block_time: meta.block_time,
+                block_height: meta.block_height,
+                executed_transaction_count: meta.executed_transaction_count,
+                unobserved_txs: meta.executed_transaction_count.saturating_sub(total_txs),
+                tx_filters: self.tx_filters.iter().cloned().collect(),
+            }),
+            block_meta_missing: false,
+            tx_by_status,
+            tx_by_program,
+            timeline,
+            signatures: None,
+        }
+    }
+}
+
+/// An update that changed tracker state, as returned by
+/// `SlotTrackerSet::apply_update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackedUpdate<'a> {
+    SlotStatus { slot: u64, status: SlotStatus },
+    Transaction { slot: u64, signature: &'a [u8] },
+    BlockMeta { slot: u64 },
+}
+
+/// All in-flight slot trackers of one stream, plus enough history to drop
+/// updates that the server replays after a `from_slot` resubscribe.
+/// Closed slots are queued as `SlotSummary` events until `drain_summaries`.
+#[derive(Debug)]
+pub struct SlotTrackerSet {
+    endpoint: String,
+    programs: Arc<ProgramRegistry>,
+    eviction: EvictionPolicy,
+    trackers: HashMap<u64, SlotTracker>,
+    completed: BTreeSet<u64>,
+    highest_seen_slot: Option<u64>,
+    summaries: Vec<SlotSummary>,
+    // Подписаны ли на blocks_meta: без нее отсутствие meta не считается пропуском
+    block_meta_expected: bool,
+    // Сигнатуры нужны для дедупликации всегда, а в сводку копируем только по запросу
+    signatures_included: bool,
+}
+
+impl SlotTrackerSet {
+    pub fn new(endpoint: String, programs: Arc<ProgramRegistry>, eviction: EvictionPolicy) -> Self {
+        Self {
+            endpoint,
+            programs,
+            eviction,
+            trackers: HashMap::new(),
+            completed: BTreeSet::new(),
+            highest_seen_slot: None,
+            summaries: Vec::new(),
+            block_meta_expected: false,
+            signatures_included: false,
+        }
+    }
+
+    /// Replaces the registry used to match transactions, e.g. after programs
+    /// were enabled or disabled at runtime.
+    pub fn set_programs(&mut self, programs: Arc<ProgramRegistry>) {
+        self.programs = programs;
+    }
+
+    /// Finalized slots closed without block meta are reported as missing it
+    /// only when `expected`, i.e. with a `blocks_meta` subscription.
+    pub fn expect_block_meta(&mut self, expected: bool) {
+        self.block_meta_expected = expected;
+    }
+
+    /// Adds the signatures of the counted transactions to slot summaries.
+    pub fn include_signatures(&mut self, include: bool) {
+        self.signatures_included = include;
+    }
+
+    /// Applies a slot, transaction or block meta update received at `now` (microseconds,
+    /// see `monotonic_time_us`). Returns what changed, or `None` for other update kinds
+    /// and for replays of updates already applied.
+    pub fn apply_update<'a>(
+        &mut self,
+        update: &'a SubscribeUpdate,
+        now: u64,
+    ) -> Result<Option<TrackedUpdate<'a>>> {
+        match &update.update_oneof {
+            Some(UpdateOneof::Slot(slot)) => {
+                let status = SlotStatus::try_from(slot.status)?;
+                let applied = self.apply_slot_status(slot.slot, status, now);
+                Ok(applied.then_some(TrackedUpdate::SlotStatus {
+                    slot: slot.slot,
+                    status,
+                }))
+            }
+            Some(UpdateOneof::Transaction(transaction)) => {
+                let Some(tx_info) = &transaction.transaction else {
+                    return Ok(None);
+                };
+                let programs = self
+                    .programs
+                    .match_accounts(transaction_account_keys(tx_info));
+                let applied = self.apply_transaction(
+                    transaction.slot,
+                    &tx_info.signature,
+                    tx_info.index,
+                    &programs,
+                    now,
+                );
+                if applied && let Some(tracker) = self.trackers.get_mut(&transaction.slot) {
+                    tracker.add_tx_filters(&update.filters);
+                }
+                Ok(applied.then_some(TrackedUpdate::Transaction {
+                    slot: transaction.slot,
+                    signature: &tx_info.signature,
+                }))
+            }
+            Some(UpdateOneof::BlockMeta(block_meta)) => {
+                let meta = BlockMeta {
+                    blockhash: block_meta.blockhash.clone(),
+                    block_time: block_meta.block_time.map(|time| time.timestamp),
+                    block_height: block_meta.block_height.map(|height| height.block_height),
+                    executed_transaction_count: block_meta.executed_transaction_count,
+                };
+                let applied = self.apply_block_meta(block_meta.slot, meta, now);
+                Ok(applied.then_some(TrackedUpdate::BlockMeta {
+                    slot: block_meta.slot,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Summaries of the slots closed since the last call, in closing order.
+    pub fn drain_summaries(&mut self) -> Vec<SlotSummary> {
+        std::mem::take(&mut self.summaries)
+    }
+
+    /// Returns `false` if the update is a replay of one already applied.
+    /// `now` is the update's receive time in microseconds.
+    pub fn apply_slot_status(&mut self, slot: u64, status: SlotStatus, now: u64) -> bool {
+        if self.completed.contains(&slot) {
+            return false;
+        }
+        self.observe_slot(slot);
+
+        let applied = if status == SlotStatus::SlotFinalized || status == SlotStatus::SlotDead {
+            if let Some(tracker) = self.trackers.get_mut(&slot) {
+                tracker.update_status(status, now);
+            }
+            let outcome = if status == SlotStatus::SlotFinalized {
+                SlotOutcome::Finalized
+            } else {
+                SlotOutcome::Dead
+            };
+            self.complete(slot, outcome);
+            true
+        } else {
+            let tracker = self.trackers.entry(slot).or_insert_with(|| {
+                SlotTracker::new(slot, format!("slot_update_{:?}", status), now)
+            });
+            tracker.update_status(status, now)
+        };
+
+        self.evict(now);
+        applied
+    }
+
+    /// Flushes trackers that exceeded the eviction policy as of `now`.
+    pub fn evict(&mut self, now: u64) {
+        let highest = self.highest_seen_slot.unwrap_or(0);
+        let mut expired: Vec<(u64, EvictionReason)> = self
+            .trackers
+            .values()
+            .filter_map(|tracker| {
+                if let Some(max_distance) = self.eviction.max_slot_distance
+                    && highest.saturating_sub(tracker.slot) > max_distance
+                {
+                    return Some((tracker.slot, EvictionReason::Distance));
+                }
+                if let Some(max_age_ms) = self.eviction.max_age_ms
+                    && now.saturating_sub(tracker.create_ts) > max_age_ms.saturating_mul(1000)
+                {
+                    return Some((tracker.slot, EvictionReason::Age));
+                }
+                None
+            })
+            .collect();
+        expired.sort_unstable_by_key(|(slot, _)| *slot);
+
+        for (slot, reason) in expired {
+            self.complete(slot, SlotOutcome::Evicted(reason));
+        }
+    }
+
+    fn complete(&mut self, slot: u64, outcome: SlotOutcome) {
+        if let Some(tracker) = self.trackers.remove(&slot) {
+            let mut summary = tracker.summarize(outcome, &self.endpoint, &self.programs);
+            // Meta приходит для каждого собранного блока, у мертвых слотов ее нет
+            summary.block_meta_missing = self.block_meta_expected
+                && outcome == SlotOutcome::Finalized
+                && summary.block_meta.is_none();
+            if self.signatures_included {
+                summary.signatures = Some(tracker.signatures.into_iter().collect());
+            }
+            self.summaries.push(summary);
+        }
+        self.mark_completed(slot);
+    }
+
+    /// Returns `false` if the transaction is a replay of one already applied.
+    pub fn apply_transaction(
+        &mut self,
+        slot: u64,
+        signature: &[u8],
+        index: u64,
+        programs: &[usize],
+        now: u64,
+    ) -> bool {
+        if self.completed.contains(&slot) {
+            return false;
+        }
+        self.observe_slot(slot);
+
+        let tracker = self
+            .trackers
+            .entry(slot)
+            .or_insert_with(|| SlotTracker::new(slot, "transaction".to_string(), now));
+        tracker.apply_transaction(signature, index, programs, now)
+    }
+
+    /// Returns `false` if the slot is closed or already has its block meta.
+    pub fn apply_block_meta(&mut self, slot: u64, meta: BlockMeta, now: u64) -> bool {
+        if self.completed.contains(&slot) {
+            return false;
+        }
+        self.observe_slot(slot);
+
+        let tracker = self
+            .trackers
+            .entry(slot)
+            .or_insert_with(|| SlotTracker::new(slot, "block_meta".to_string(), now));
+        tracker.set_block_meta(meta)
+    }
+
+    /// Slot to pass as `from_slot` when resubscribing: the oldest slot that is
+    /// still being tracked, so nothing in flight is lost, or the last slot seen.
+    pub fn resume_slot(&self) -> Option<u64> {
+        self.trackers
+            .keys()
+            .min()
+            .copied()
+            .or(self.highest_seen_slot)
+    }
+
+    /// Number of slots currently being tracked.
+    pub fn in_flight(&self) -> usize {
+        self.trackers.len()
+    }
+
+    fn observe_slot(&mut self, slot: u64) {
+        self.highest_seen_slot = Some(self.highest_seen_slot.map_or(slot, |s| s.max(slot)));
+    }
+
+    fn mark_completed(&mut self, slot: u64) {
+        self.completed.insert(slot);
+        while self.completed.len() > COMPLETED_SLOTS_RETAINED {
+            self.completed.pop_first();
+        }
+    }
+}
+
+/// Static account keys of the message followed by addresses loaded from lookup tables.
+fn transaction_account_keys(
+    tx_info: &SubscribeUpdateTransactionInfo,
+) -> impl Iterator<Item = &[u8]> {
+    let static_keys = tx_info
+        .transaction
+        .iter()
+        .filter_map(|tx| tx.message.as_ref())
+        .flat_map(|message| message.account_keys.iter());
+    let loaded_keys = tx_info.meta.iter().flat_map(|meta| {
+        meta.loaded_writable_addresses
+            .iter()
+            .chain(meta.loaded_readonly_addresses.iter())
+    });
+    static_keys.chain(loaded_keys).map(Vec::as_slice)
+}
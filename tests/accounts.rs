@@ -0,0 +1,131 @@
+mod common;
+
+use common::{RAYDIUM, WHIRLPOOL};
+use grpc_connect_test::accounts::{Program, ProgramRegistry, decode_pubkey};
+use std::path::PathBuf;
+
+fn program(id: &str, code: &str, name: &str) -> Program {
+    Program {
+        id: id.to_string(),
+        code: code.to_string(),
+        name: name.to_string(),
+        enabled: true,
+    }
+}
+
+/// Writes `contents` to a per-process temp file with the given extension.
+fn registry_file(name: &str, ext: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "grpc-connect-test-{name}-{}.{ext}",
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn rejection(programs: Vec<Program>) -> String {
+    format!("{:#}", ProgramRegistry::new(programs).unwrap_err())
+}
+
+#[test]
+fn builtin_registry_is_valid() {
+    let registry = ProgramRegistry::builtin();
+    assert!(registry.enabled_ids().iter().any(|id| id == RAYDIUM));
+}
+
+#[test]
+fn registry_loads_from_toml() {
+    let path = registry_file(
+        "registry",
+        "toml",
+        &format!(
+            r#"
+[[program]]
+id = "{RAYDIUM}"
+code = "R"
+name = "Raydium"
+
+[[program]]
+id = "{WHIRLPOOL}"
+code = "W"
+name = "Whirlpool"
+enabled = false
+"#
+        ),
+    );
+    let registry = ProgramRegistry::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(registry.enabled_ids(), vec![RAYDIUM.to_string()]);
+    let key = decode_pubkey(WHIRLPOOL).unwrap();
+    assert!(registry.match_accounts([key.as_slice()]).is_empty());
+}
+
+#[test]
+fn registry_loads_from_json() {
+    let path = registry_file(
+        "registry",
+        "json",
+        &format!(
+            r#"{{"programs": [
+                {{"id": "{RAYDIUM}", "code": "R", "name": "Raydium"}},
+                {{"id": "{WHIRLPOOL}", "code": "W", "name": "Whirlpool"}}
+            ]}}"#
+        ),
+    );
+    let registry = ProgramRegistry::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        registry.enabled_ids(),
+        vec![RAYDIUM.to_string(), WHIRLPOOL.to_string()]
+    );
+    let key = decode_pubkey(WHIRLPOOL).unwrap();
+    let matched = registry.match_accounts([key.as_slice()]);
+    assert_eq!(matched.len(), 1);
+    assert_eq!(registry.get(matched[0]).code, "W");
+}
+
+#[test]
+fn registry_rejects_invalid_programs() {
+    let error = rejection(vec![program("not-base58-0OIl", "X", "Broken")]);
+    assert!(error.contains("not valid base58"), "{error}");
+
+    // Валидный base58, но не 32 байта
+    let error = rejection(vec![program("3yZe7d", "X", "Short")]);
+    assert!(error.contains("expected 32"), "{error}");
+
+    let error = rejection(vec![
+        program(RAYDIUM, "R", "Raydium"),
+        program(RAYDIUM, "R2", "Raydium again"),
+    ]);
+    assert!(error.contains("duplicate program id"), "{error}");
+
+    let error = rejection(vec![
+        program(RAYDIUM, "X", "Raydium"),
+        program(WHIRLPOOL, "X", "Whirlpool"),
+    ]);
+    assert!(error.contains("duplicate program code X"), "{error}");
+}
+
+#[test]
+fn programs_are_toggled_by_id_code_or_name() {
+    let mut registry = ProgramRegistry::new(vec![
+        program(RAYDIUM, "R", "Raydium"),
+        program(WHIRLPOOL, "W", "Whirlpool"),
+    ])
+    .unwrap();
+
+    registry.set_enabled("R", false).unwrap();
+    assert_eq!(registry.enabled_ids(), vec![WHIRLPOOL.to_string()]);
+    registry.set_enabled("Whirlpool", false).unwrap();
+    assert!(registry.enabled_ids().is_empty());
+    registry.set_enabled(RAYDIUM, true).unwrap();
+    assert_eq!(registry.enabled_ids(), vec![RAYDIUM.to_string()]);
+
+    let error = registry.set_enabled("nope", true).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown program nope"),
+        "{error}"
+    );
+}
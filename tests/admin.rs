@@ -0,0 +1,189 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, Step};
+use common::{
+    Monitor, RAYDIUM, WHIRLPOOL, eventually, free_port, http_request, slot_update, transaction,
+};
+use std::time::Duration;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+#[tokio::test]
+async fn programs_are_changed_on_the_live_stream() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(800, SlotStatus::SlotProcessed),
+        // Даем время поменять подписку до прихода транзакции
+        Step::Sleep(Duration::from_millis(1500)),
+        transaction(800, 1, &[RAYDIUM, WHIRLPOOL]),
+        slot_update(800, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let admin_port = free_port().await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--admin-listen",
+        &format!("127.0.0.1:{admin_port}"),
+        "--disable-program",
+        "W",
+        "--ping-interval-ms",
+        "0",
+    ])
+    .await;
+    monitor
+        .wait_for_line(|line| line.ends_with("Listening for updates..."))
+        .await;
+
+    let (status, body) = http_request(admin_port, "GET", "/subscription", "").await;
+    assert_eq!(status, 200);
+    assert!(body.contains(RAYDIUM), "{body}");
+    assert!(!body.contains(WHIRLPOOL), "{body}");
+    assert!(body.contains(r#""commitment":"confirmed""#), "{body}");
+
+    let (status, body) = http_request(
+        admin_port,
+        "POST",
+        "/subscription/programs",
+        r#"{"enable":["W"],"disable":["R"]}"#,
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains(WHIRLPOOL), "{body}");
+    assert!(!body.contains(RAYDIUM), "{body}");
+
+    // Новый запрос уходит по тому же стриму, без переподключения
+    let requests = eventually(|| {
+        let requests = server.requests();
+        (requests.len() >= 2).then_some(requests)
+    })
+    .await;
+    let include = &requests[1].transactions["amm_transactions"].account_include;
+    assert!(include.contains(&WHIRLPOOL.to_string()));
+    assert!(!include.contains(&RAYDIUM.to_string()));
+    assert_eq!(requests[1].from_slot, None);
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:800"))
+        .await;
+    assert!(summary.contains("tx_by_program:[W:1]"), "{summary}");
+}
+
+#[tokio::test]
+async fn unknown_program_is_rejected() {
+    let server = MockGeyser::start(vec![vec![]]).await;
+    let admin_port = free_port().await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--admin-listen",
+        &format!("127.0.0.1:{admin_port}"),
+    ])
+    .await;
+    monitor
+        .wait_for_line(|line| line.ends_with("Listening for updates..."))
+        .await;
+
+    let (status, body) = http_request(
+        admin_port,
+        "POST",
+        "/subscription/programs",
+        r#"{"enable":["no-such-program"]}"#,
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert!(body.contains("unknown program no-such-program"), "{body}");
+
+    let (status, _) = http_request(
+        admin_port,
+        "POST",
+        "/subscription/programs",
+        r#"{"disable":["R","W"]}"#,
+    )
+    .await;
+    assert_eq!(status, 200);
+    eventually(|| (server.requests().len() == 2).then_some(())).await;
+}
+
+#[tokio::test]
+async fn new_program_is_added_to_the_live_stream() {
+    // Программы нет во встроенном реестре
+    let program = bs58::encode([7u8; 32]).into_string();
+    let server = MockGeyser::start(vec![vec![
+        slot_update(810, SlotStatus::SlotProcessed),
+        Step::Sleep(Duration::from_millis(1500)),
+        transaction(810, 1, &[&program]),
+        slot_update(810, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let admin_port = free_port().await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--admin-listen",
+        &format!("127.0.0.1:{admin_port}"),
+        "--ping-interval-ms",
+        "0",
+    ])
+    .await;
+    monitor
+        .wait_for_line(|line| line.ends_with("Listening for updates..."))
+        .await;
+
+    // Повтор существующего id отклоняется целиком
+    let (status, body) = http_request(
+        admin_port,
+        "POST",
+        "/subscription/programs",
+        &format!(r#"{{"add":[{{"id":"{RAYDIUM}","code":"R9","name":"Raydium copy"}}]}}"#),
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert!(body.contains("duplicate program id"), "{body}");
+
+    let (status, body) = http_request(
+        admin_port,
+        "POST",
+        "/subscription/programs",
+        &format!(r#"{{"add":[{{"id":"{program}","code":"N","name":"New DEX"}}]}}"#),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains(&program), "{body}");
+    assert!(body.contains(RAYDIUM), "{body}");
+
+    let requests = eventually(|| {
+        let requests = server.requests();
+        (requests.len() >= 2).then_some(requests)
+    })
+    .await;
+    let include = &requests[1].transactions["amm_transactions"].account_include;
+    assert!(include.contains(&program));
+    assert!(include.contains(&RAYDIUM.to_string()));
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:810"))
+        .await;
+    assert!(summary.contains("tx_by_program:[N:1]"), "{summary}");
+}
+
+#[tokio::test]
+async fn admin_routes_are_off_without_admin_listen() {
+    let server = MockGeyser::start(vec![vec![]]).await;
+    let mut monitor = Monitor::spawn(&["-e", &server.endpoint()]).await;
+    monitor
+        .wait_for_line(|line| line.ends_with("Listening for updates..."))
+        .await;
+
+    // Порт метрик открыт наружу и подписку менять не дает
+    let (status, _) = http_request(
+        monitor.metrics_port,
+        "POST",
+        "/subscription/programs",
+        r#"{"disable":["R"]}"#,
+    )
+    .await;
+    assert_eq!(status, 404);
+    let (status, _) = monitor.http_get("/subscription").await;
+    assert_eq!(status, 404);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Server, ServerTlsConfig};
+use tonic::{Request, Response, Status, Streaming};
+use yellowstone_grpc_proto::geyser::geyser_server::{Geyser, GeyserServer};
+use yellowstone_grpc_proto::geyser::{
+    GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+    GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+    GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+    PongResponse, SubscribeReplayInfoRequest, SubscribeReplayInfoResponse, SubscribeRequest,
+    SubscribeUpdate, SubscribeUpdatePong, subscribe_update::UpdateOneof,
+};
+
+/// One scripted action of a subscribe session.
+#[derive(Debug, Clone)]
+pub enum Step {
+    Update(Box<SubscribeUpdate>),
+    Sleep(Duration),
+    /// Fails the stream with this status and ends the session.
+    Fail(Status),
+    /// Ends the stream cleanly.
+    Close,
+}
+
+/// Fake Yellowstone Geyser server. Every `Subscribe` call plays the next
+/// scripted session; once the script runs out, streams stay open and idle.
+pub struct MockGeyser {
+    sessions: Mutex<VecDeque<Vec<Step>>>,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+}
+
+pub struct MockGeyserHandle {
+    pub addr: SocketAddr,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+}
+
+impl MockGeyserHandle {
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn tls_endpoint(&self) -> String {
+        format!("https://{}", self.addr)
+    }
+
+    /// Every `SubscribeRequest` received so far, across all sessions.
+    pub fn requests(&self) -> Vec<SubscribeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl MockGeyser {
+    pub async fn start(sessions: Vec<Vec<Step>>) -> MockGeyserHandle {
+        Self::start_with_tls(sessions, None).await
+    }
+
+    /// Same as `start`, but serving TLS when `tls` is given.
+    pub async fn start_with_tls(
+        sessions: Vec<Vec<Step>>,
+        tls: Option<ServerTlsConfig>,
+    ) -> MockGeyserHandle {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let service = MockGeyser {
+            sessions: Mutex::new(sessions.into()),
+            requests: requests.clone(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut server = Server::builder();
+            if let Some(tls) = tls {
+                server = server.tls_config(tls).unwrap();
+            }
+            server
+                .add_service(GeyserServer::new(service))
+                .serve_with_incoming(TcpIncoming::from(listener))
+                .await
+                .unwrap();
+        });
+
+        MockGeyserHandle { addr, requests }
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Geyser for MockGeyser {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut incoming = request.into_inner();
+        let steps = self.sessions.lock().unwrap().pop_front();
+        let (tx, rx) = mpsc::channel(1024);
+
+        // Запоминаем все запросы клиента, включая повторные по тому же стриму,
+        // и отвечаем на пинги, как настоящий сервер
+        let requests = self.requests.clone();
+        let pong_tx = tx.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(request)) = incoming.message().await {
+                let ping = request.ping;
+                requests.lock().unwrap().push(request);
+                if let Some(ping) = ping {
+                    let pong = SubscribeUpdate {
+                        filters: vec![],
+                        created_at: Some(SystemTime::now().into()),
+                        update_oneof: Some(UpdateOneof::Pong(SubscribeUpdatePong { id: ping.id })),
+                    };
+                    if pong_tx.send(Ok(pong)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            for step in steps.unwrap_or_default() {
+                match step {
+                    Step::Update(update) => {
+                        if tx.send(Ok(*update)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Step::Sleep(duration) => tokio::time::sleep(duration).await,
+                    Step::Fail(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        return;
+                    }
+                    Step::Close => return,
+                }
+            }
+            // Скрипт закончился - держим стрим открытым, пока клиент не уйдет
+            tx.closed().await;
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn subscribe_replay_info(
+        &self,
+        _request: Request<SubscribeReplayInfoRequest>,
+    ) -> Result<Response<SubscribeReplayInfoResponse>, Status> {
+        Err(Status::unimplemented("not supported by the mock"))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        Err(Status::unimplemented("not supported by the mock"))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        Err(Status::unimplemented("not supported by the mock"))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        Err(Status::unimplemented("not supported by the mock"))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        _request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        Err(Status::unimplemented("not supported by the mock"))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        Ok(Response::new(GetVersionResponse {
+            version: "mock".to_string(),
+        }))
+    }
+}
@@ -0,0 +1,237 @@
+#![allow(dead_code)]
+
+pub mod mock_geyser;
+
+use mock_geyser::Step;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use yellowstone_grpc_proto::geyser::{
+    SlotStatus, SubscribeUpdate, SubscribeUpdateBlockMeta, SubscribeUpdatePing,
+    SubscribeUpdateSlot, SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo,
+    subscribe_update::UpdateOneof,
+};
+use yellowstone_grpc_proto::prelude::{BlockHeight, Message, Transaction};
+
+pub const RAYDIUM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+pub const WHIRLPOOL: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub fn slot_update(slot: u64, status: SlotStatus) -> Step {
+    Step::Update(Box::new(SubscribeUpdate {
+        filters: vec!["client".to_string()],
+        created_at: Some(SystemTime::now().into()),
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            parent: slot.checked_sub(1),
+            status: status as i32,
+            dead_error: None,
+        })),
+    }))
+}
+
+/// Block meta of `slot` reporting `executed` transactions.
+pub fn block_meta(slot: u64, executed: u64) -> Step {
+    Step::Update(Box::new(SubscribeUpdate {
+        filters: vec!["blocks_meta".to_string()],
+        created_at: Some(SystemTime::now().into()),
+        update_oneof: Some(UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot,
+            blockhash: format!("hash{slot}"),
+            block_height: Some(BlockHeight {
+                block_height: slot - 10,
+            }),
+            executed_transaction_count: executed,
+            ..Default::default()
+        })),
+    }))
+}
+
+/// A keepalive ping from the server.
+pub fn ping() -> Step {
+    Step::Update(Box::new(SubscribeUpdate {
+        filters: vec![],
+        created_at: Some(SystemTime::now().into()),
+        update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+    }))
+}
+
+/// A transaction in `slot` whose account keys include `programs`. `id` makes
+/// the signature unique.
+pub fn transaction(slot: u64, id: u8, programs: &[&str]) -> Step {
+    let account_keys = programs
+        .iter()
+        .map(|program| bs58::decode(program).into_vec().unwrap())
+        .collect();
+    Step::Update(Box::new(SubscribeUpdate {
+        filters: vec!["amm_transactions".to_string()],
+        created_at: Some(SystemTime::now().into()),
+        update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            slot,
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![id; 64],
+                is_vote: false,
+                transaction: Some(Transaction {
+                    signatures: vec![vec![id; 64]],
+                    message: Some(Message {
+                        account_keys,
+                        ..Default::default()
+                    }),
+                }),
+                meta: None,
+                index: id as u64,
+            }),
+        })),
+    }))
+}
+
+/// The monitor binary running against a mock server, with its stdout and
+/// stderr merged into one line stream.
+pub struct Monitor {
+    child: Child,
+    lines: mpsc::UnboundedReceiver<String>,
+    pub metrics_port: u16,
+}
+
+impl Monitor {
+    pub async fn spawn(args: &[&str]) -> Self {
+        let metrics_port = free_port().await;
+        let mut child = Command::new(env!("CARGO_BIN_EXE_grpc-connect-test"))
+            .args(args)
+            .args(["--metrics-port", &metrics_port.to_string()])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("failed to start monitor binary");
+
+        let (tx, lines) = mpsc::unbounded_channel();
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        for reader in [
+            Box::new(stdout) as Box<dyn tokio::io::AsyncRead + Unpin + Send>,
+            Box::new(stderr),
+        ] {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = reader.next_line().await {
+                    let _ = tx.send(line);
+                }
+            });
+        }
+
+        Self {
+            child,
+            lines,
+            metrics_port,
+        }
+    }
+
+    /// Waits for the next output line matching `pred`, skipping others.
+    pub async fn wait_for_line(&mut self, pred: impl Fn(&str) -> bool) -> String {
+        let wait = async {
+            while let Some(line) = self.lines.recv().await {
+                if pred(&line) {
+                    return line;
+                }
+            }
+            panic!("monitor exited before the expected line");
+        };
+        tokio::time::timeout(WAIT_TIMEOUT, wait)
+            .await
+            .expect("timed out waiting for monitor output")
+    }
+
+    /// Waits for the process to exit on its own and returns its exit code.
+    pub async fn wait_for_exit(&mut self) -> Option<i32> {
+        tokio::time::timeout(WAIT_TIMEOUT, self.child.wait())
+            .await
+            .expect("timed out waiting for monitor to exit")
+            .unwrap()
+            .code()
+    }
+
+    /// Sends SIGTERM, as a service manager would on stop.
+    pub fn terminate(&self) {
+        let pid = self.child.id().expect("monitor already exited");
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &pid.to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    pub async fn metrics(&self) -> String {
+        self.http_get("/metrics").await.1
+    }
+
+    /// Returns the status code and body of a GET to the monitor's HTTP server.
+    pub async fn http_get(&self, path: &str) -> (u16, String) {
+        http_request(self.metrics_port, "GET", path, "").await
+    }
+}
+
+/// Minimal HTTP/1.1 client so the tests don't need an HTTP dependency.
+pub async fn http_request(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = None;
+    for _ in 0..50 {
+        match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    let mut stream = stream.expect("monitor HTTP server did not start");
+
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0);
+    (status, body.to_string())
+}
+
+/// Polls `check` until it returns `Some`, failing the test after the usual timeout.
+pub async fn eventually<T>(check: impl Fn() -> Option<T>) -> T {
+    let wait = async {
+        loop {
+            if let Some(value) = check() {
+                return value;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(WAIT_TIMEOUT, wait)
+        .await
+        .expect("timed out waiting for condition")
+}
+
+/// Value of an exact Prometheus sample line such as `name{label="x"}`.
+pub fn metric_value(metrics: &str, sample: &str) -> Option<f64> {
+    metrics.lines().find_map(|line| {
+        let (name, value) = line.rsplit_once(' ')?;
+        (name == sample).then(|| value.parse().ok()).flatten()
+    })
+}
+
+pub async fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
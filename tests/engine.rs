@@ -0,0 +1,175 @@
+mod common;
+
+use common::mock_geyser::Step;
+use common::{RAYDIUM, WHIRLPOOL, slot_update, transaction};
+use grpc_connect_test::accounts::ProgramRegistry;
+use grpc_connect_test::sink::{SlotSink, dispatch};
+use grpc_connect_test::summary::SlotSummary;
+use grpc_connect_test::tracker::{
+    EvictionPolicy, EvictionReason, SlotOutcome, SlotTrackerSet, TrackedUpdate,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yellowstone_grpc_proto::geyser::{SlotStatus, SubscribeUpdate};
+
+fn update(step: Step) -> SubscribeUpdate {
+    match step {
+        Step::Update(update) => *update,
+        step => panic!("not an update: {step:?}"),
+    }
+}
+
+#[test]
+fn engine_emits_summaries_for_closed_slots() {
+    let mut trackers = SlotTrackerSet::new(
+        "lib".to_string(),
+        Arc::new(ProgramRegistry::builtin()),
+        EvictionPolicy::default(),
+    );
+    trackers.include_signatures(true);
+
+    let updates = [
+        (slot_update(42, SlotStatus::SlotProcessed), 1_000_000),
+        (transaction(42, 1, &[RAYDIUM]), 1_010_250),
+        (transaction(42, 2, &[RAYDIUM, WHIRLPOOL]), 1_030_500),
+        (transaction(42, 2, &[RAYDIUM, WHIRLPOOL]), 1_031_000),
+        (slot_update(42, SlotStatus::SlotFinalized), 1_500_000),
+    ]
+    .map(|(step, now)| (update(step), now));
+
+    let applied = updates
+        .iter()
+        .map(|(update, now)| trackers.apply_update(update, *now).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        applied[0],
+        Some(TrackedUpdate::SlotStatus {
+            slot: 42,
+            status: SlotStatus::SlotProcessed
+        })
+    );
+    assert!(matches!(
+        applied[1],
+        Some(TrackedUpdate::Transaction { slot: 42, .. })
+    ));
+    // Повтор той же транзакции не учитывается
+    assert_eq!(applied[3], None);
+
+    let summaries = trackers.drain_summaries();
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0];
+    assert_eq!(summary.outcome, SlotOutcome::Finalized);
+    assert_eq!(summary.slot, 42);
+    assert_eq!(summary.endpoint, "lib");
+    // Длительность считается в микросекундах
+    assert_eq!(summary.duration_us, 20_250);
+    assert_eq!(summary.total_txs, 2);
+    assert_eq!(summary.duplicate_txs, 1);
+    assert_eq!(
+        summary.signatures,
+        Some(vec![
+            bs58::encode([1u8; 64]).into_string(),
+            bs58::encode([2u8; 64]).into_string(),
+        ])
+    );
+    assert_eq!(
+        summary.tx_by_status,
+        vec![(Some(SlotStatus::SlotProcessed), 2)]
+    );
+    assert_eq!(summary.timeline[1].received_at_us, 1_500_000);
+    assert!(
+        summary.text_line().contains(" duration:20.250ms "),
+        "{}",
+        summary.text_line()
+    );
+    assert!(trackers.drain_summaries().is_empty());
+}
+
+#[derive(Default)]
+struct CountingSink {
+    completed: AtomicUsize,
+    dead: AtomicUsize,
+    evicted: AtomicUsize,
+}
+
+impl SlotSink for CountingSink {
+    fn slot_completed(&self, _summary: &SlotSummary) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn slot_dead(&self, _summary: &SlotSummary) {
+        self.dead.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn slot_evicted(&self, _summary: &SlotSummary, reason: EvictionReason) {
+        assert_eq!(reason, EvictionReason::Distance);
+        self.evicted.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn summaries_are_dispatched_by_outcome() {
+    let mut trackers = SlotTrackerSet::new(
+        "lib".to_string(),
+        Arc::new(ProgramRegistry::builtin()),
+        EvictionPolicy {
+            max_age_ms: None,
+            max_slot_distance: Some(10),
+        },
+    );
+    let sink = CountingSink::default();
+
+    for step in [
+        slot_update(1, SlotStatus::SlotProcessed),
+        slot_update(2, SlotStatus::SlotProcessed),
+        slot_update(3, SlotStatus::SlotProcessed),
+        slot_update(2, SlotStatus::SlotFinalized),
+        slot_update(3, SlotStatus::SlotDead),
+        slot_update(20, SlotStatus::SlotProcessed),
+    ] {
+        trackers.apply_update(&update(step), 0).unwrap();
+        for summary in trackers.drain_summaries() {
+            dispatch(&sink, &summary);
+        }
+    }
+
+    assert_eq!(sink.completed.load(Ordering::Relaxed), 1);
+    assert_eq!(sink.dead.load(Ordering::Relaxed), 1);
+    assert_eq!(sink.evicted.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn transaction_order_is_compared_with_block_index() {
+    let mut trackers = SlotTrackerSet::new(
+        "lib".to_string(),
+        Arc::new(ProgramRegistry::builtin()),
+        EvictionPolicy::default(),
+    );
+
+    // Индекс транзакции в тестовом хелпере совпадает с ее id
+    for (step, now) in [
+        (slot_update(7, SlotStatus::SlotProcessed), 1_000_000),
+        (transaction(7, 5, &[RAYDIUM]), 1_001_000),
+        (transaction(7, 3, &[RAYDIUM]), 1_002_000),
+        (transaction(7, 9, &[RAYDIUM]), 1_000_500),
+        (transaction(7, 9, &[RAYDIUM]), 1_004_000),
+        (slot_update(7, SlotStatus::SlotFinalized), 1_500_000),
+    ] {
+        trackers.apply_update(&update(step), now).unwrap();
+    }
+
+    let summaries = trackers.drain_summaries();
+    let tx_index = summaries[0].tx_index.as_ref().unwrap();
+    assert_eq!(tx_index.min_index, 3);
+    assert_eq!(tx_index.max_index, 9);
+    assert_eq!(tx_index.out_of_order_txs, 1);
+    assert_eq!(tx_index.missing_indices, 4);
+    assert_eq!(tx_index.first_to_last_index_us, -1_500);
+    assert!(
+        summaries[0]
+            .text_line()
+            .contains(" tx_index:[3-9 out_of_order:1 missing:4 first_to_last:-1.500ms] "),
+        "{}",
+        summaries[0].text_line()
+    );
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::mock_geyser::MockGeyser;
+use common::{Monitor, RAYDIUM, WHIRLPOOL, eventually, slot_update, transaction};
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+#[tokio::test]
+async fn slot_summaries_are_exported_to_csv() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(500, SlotStatus::SlotProcessed),
+        transaction(500, 1, &[RAYDIUM]),
+        slot_update(500, SlotStatus::SlotConfirmed),
+        transaction(500, 2, &[RAYDIUM, WHIRLPOOL]),
+        slot_update(500, SlotStatus::SlotFinalized),
+        transaction(501, 3, &[WHIRLPOOL]),
+        slot_update(501, SlotStatus::SlotDead),
+    ]])
+    .await;
+    let dir = std::env::temp_dir().join(format!("grpc-connect-test-export-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let _monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--export-dir",
+        dir.to_str().unwrap(),
+        "--export-rotation",
+        "daily",
+    ])
+    .await;
+
+    let contents = eventually(|| {
+        let file = std::fs::read_dir(&dir).ok()?.next()?.ok()?;
+        let contents = std::fs::read_to_string(file.path()).ok()?;
+        (contents.lines().count() == 3).then_some((file.file_name(), contents))
+    })
+    .await;
+    let (file_name, contents) = contents;
+    let file_name = file_name.to_str().unwrap();
+    assert!(
+        file_name.starts_with("slots-20") && file_name.ends_with(".csv"),
+        "{file_name}"
+    );
+
+    let mut lines = contents.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    let column = |row: &Vec<&str>, name: &str| {
+        let index = header.iter().position(|column| *column == name).unwrap();
+        row[index].to_string()
+    };
+
+    let finalized = &rows[0];
+    assert_eq!(column(finalized, "endpoint"), "mock");
+    assert_eq!(column(finalized, "slot"), "500");
+    assert_eq!(column(finalized, "outcome"), "finalized");
+    assert_eq!(column(finalized, "eviction_reason"), "");
+    assert_eq!(column(finalized, "total_txs"), "2");
+    assert_eq!(column(finalized, "tx_processed"), "1");
+    assert_eq!(column(finalized, "tx_confirmed"), "1");
+    assert!(!column(finalized, "confirmed_at_us").is_empty());
+    assert_eq!(column(finalized, "first_shred_received_at_us"), "");
+    assert_eq!(column(finalized, "tx_by_program"), "W:1 R:2");
+    assert_eq!(column(finalized, "min_tx_index"), "1");
+    assert_eq!(column(finalized, "max_tx_index"), "2");
+    assert_eq!(column(finalized, "out_of_order_txs"), "0");
+
+    let dead = &rows[1];
+    assert_eq!(column(dead, "slot"), "501");
+    assert_eq!(column(dead, "outcome"), "dead");
+    assert_eq!(column(dead, "tx_no_status_yet"), "1");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn sinks_can_be_combined() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(510, SlotStatus::SlotProcessed),
+        transaction(510, 1, &[RAYDIUM]),
+        slot_update(510, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let dir = std::env::temp_dir().join(format!("grpc-connect-test-sinks-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let json_path = dir.join("summaries.jsonl");
+    let csv_dir = dir.join("csv");
+
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--sink",
+        "text",
+        "--sink",
+        &format!("json:{}", json_path.display()),
+        "--sink",
+        &format!("csv:{}", csv_dir.display()),
+    ])
+    .await;
+
+    monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:510"))
+        .await;
+    let json = eventually(|| {
+        std::fs::read_to_string(&json_path)
+            .ok()
+            .filter(|s| !s.is_empty())
+    })
+    .await;
+    assert!(json.starts_with(r#"{"outcome":"finalized""#), "{json}");
+    eventually(|| {
+        let file = std::fs::read_dir(&csv_dir).ok()?.next()?.ok()?;
+        let contents = std::fs::read_to_string(file.path()).ok()?;
+        (contents.lines().count() == 2).then_some(())
+    })
+    .await;
+
+    // Без prometheus среди sink'ов метрики слотов не пишутся
+    let metrics = monitor.metrics().await;
+    assert!(!metrics.contains("slot_transactions_total{"), "{metrics}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn sink_flag_conflicts_with_output_flags() {
+    let mut monitor = Monitor::spawn(&["--sink", "text", "--output-format", "json"]).await;
+    assert_eq!(monitor.wait_for_exit().await, Some(2));
+}
+
+#[tokio::test]
+async fn export_files_are_closed_on_sigterm() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(520, SlotStatus::SlotProcessed),
+        transaction(520, 1, &[RAYDIUM]),
+        slot_update(520, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let dir =
+        std::env::temp_dir().join(format!("grpc-connect-test-sigterm-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let format = if cfg!(feature = "parquet") {
+        "parquet"
+    } else {
+        "csv"
+    };
+
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--export-dir",
+        dir.to_str().unwrap(),
+        "--export-format",
+        format,
+    ])
+    .await;
+    monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:520"))
+        .await;
+
+    monitor.terminate();
+    monitor
+        .wait_for_line(|line| line.contains("Shutting down"))
+        .await;
+    assert_eq!(monitor.wait_for_exit().await, Some(0));
+
+    // Parquet пишется под именем .partial и переименовывается при закрытии
+    let files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files.len(), 1, "{files:?}");
+    assert!(files[0].ends_with(&format!(".{format}")), "{files:?}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn csv_with_other_columns_is_not_appended_to() {
+    let dir = std::env::temp_dir().join(format!("grpc-connect-test-schema-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let run = |slot: u64| {
+        let dir = dir.clone();
+        async move {
+            let server = MockGeyser::start(vec![vec![
+                slot_update(slot, SlotStatus::SlotProcessed),
+                slot_update(slot, SlotStatus::SlotFinalized),
+            ]])
+            .await;
+            let mut monitor = Monitor::spawn(&[
+                "-e",
+                &format!("mock={}", server.endpoint()),
+                "--export-dir",
+                dir.to_str().unwrap(),
+                "--export-rotation",
+                "daily",
+            ])
+            .await;
+            monitor
+                .wait_for_line(|line| line.starts_with(&format!("SLOT_FINALIZED slot:{slot}")))
+                .await;
+            monitor.terminate();
+            monitor.wait_for_exit().await;
+        }
+    };
+
+    run(530).await;
+    let first = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    // Файл, записанный прошлой версией с другим набором колонок
+    let old_contents = "endpoint,slot\nmock,1\n";
+    std::fs::write(&first, old_contents).unwrap();
+
+    run(531).await;
+    assert_eq!(std::fs::read_to_string(&first).unwrap(), old_contents);
+    let second = first.with_file_name(format!(
+        "{}-1.csv",
+        first.file_stem().unwrap().to_str().unwrap()
+    ));
+    let contents = std::fs::read_to_string(&second).unwrap();
+    let mut lines = contents.lines();
+    assert!(
+        lines.next().unwrap().ends_with(",unobserved_txs"),
+        "{contents}"
+    );
+    assert!(lines.next().unwrap().starts_with("mock,531,"), "{contents}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
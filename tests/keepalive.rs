@@ -0,0 +1,100 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, Step};
+use common::{Monitor, eventually, metric_value, ping, slot_update};
+use std::time::Duration;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+#[tokio::test]
+async fn server_ping_is_answered_over_the_stream() {
+    let server = MockGeyser::start(vec![vec![ping()]]).await;
+    let _monitor = Monitor::spawn(&["-e", &server.endpoint(), "--ping-interval-ms", "0"]).await;
+
+    let requests = eventually(|| {
+        let requests = server.requests();
+        (requests.len() >= 2).then_some(requests)
+    })
+    .await;
+    assert!(requests[0].ping.is_none());
+    assert!(requests[0].transactions.contains_key("amm_transactions"));
+    // Ответ на пинг - запрос без фильтров, только с ping
+    assert!(requests[1].ping.is_some());
+    assert!(requests[1].transactions.is_empty());
+    assert!(requests[1].slots.is_empty());
+}
+
+#[tokio::test]
+async fn client_pings_measure_round_trip_time() {
+    let server = MockGeyser::start(vec![vec![]]).await;
+    let monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--ping-interval-ms",
+        "50",
+    ])
+    .await;
+
+    eventually(|| {
+        server
+            .requests()
+            .iter()
+            .any(|r| r.ping.is_some())
+            .then_some(())
+    })
+    .await;
+    let mut rtt_count = None;
+    for _ in 0..50 {
+        let metrics = monitor.metrics().await;
+        rtt_count = metric_value(
+            &metrics,
+            r#"stream_ping_rtt_milliseconds_count{endpoint="mock"}"#,
+        );
+        if rtt_count.is_some_and(|count| count > 0.0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(rtt_count.is_some_and(|count| count > 0.0), "{rtt_count:?}");
+}
+
+#[tokio::test]
+async fn stalled_stream_is_reconnected() {
+    let server = MockGeyser::start(vec![
+        // Слот пришел, после чего сервер молчит
+        vec![slot_update(700, SlotStatus::SlotProcessed)],
+        vec![
+            Step::Sleep(Duration::from_millis(50)),
+            slot_update(700, SlotStatus::SlotFinalized),
+        ],
+    ])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--ping-interval-ms",
+        "0",
+        "--stall-timeout-ms",
+        "300",
+        "--reconnect-initial-backoff-ms",
+        "10",
+    ])
+    .await;
+
+    monitor
+        .wait_for_line(|line| line.contains("stream stalled"))
+        .await;
+    monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:700"))
+        .await;
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].from_slot, Some(700));
+    assert_eq!(
+        metric_value(
+            &monitor.metrics().await,
+            r#"stream_stalls_total{endpoint="mock"}"#
+        ),
+        Some(1.0)
+    );
+}
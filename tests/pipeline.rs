@@ -0,0 +1,536 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, Step};
+use common::{
+    Monitor, RAYDIUM, WHIRLPOOL, block_meta, eventually, metric_value, slot_update, transaction,
+};
+use std::time::Duration;
+use tonic::Status;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+#[tokio::test]
+async fn finalized_slot_is_summarized_and_exported() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(100, SlotStatus::SlotFirstShredReceived),
+        transaction(100, 1, &[RAYDIUM]),
+        slot_update(100, SlotStatus::SlotProcessed),
+        transaction(100, 2, &[RAYDIUM, WHIRLPOOL]),
+        transaction(100, 3, &[WHIRLPOOL]),
+        slot_update(100, SlotStatus::SlotConfirmed),
+        slot_update(100, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&["-e", &format!("mock={}", server.endpoint())]).await;
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:100"))
+        .await;
+    assert!(summary.contains("total_txs:3"), "{summary}");
+    assert!(summary.contains("SlotFirstShredReceived:1"), "{summary}");
+    assert!(summary.contains("SlotProcessed:2"), "{summary}");
+    assert!(summary.contains("tx_by_program:[W:2 R:2]"), "{summary}");
+    assert!(
+        summary.contains("timeline:[SlotFirstShredReceived:+0.000 SlotProcessed:+"),
+        "{summary}"
+    );
+    assert!(summary.contains(" SlotFinalized:+"), "{summary}");
+    assert!(summary.ends_with("endpoint:mock"), "{summary}");
+
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_transactions_total{endpoint="mock",status="processed"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_transactions_total{endpoint="mock",status="first_shred_received"}"#
+        ),
+        Some(1.0)
+    );
+    // Старые имена остаются, пока дашборды не переведены
+    assert_eq!(
+        metric_value(&metrics, "slot_transactions_processed"),
+        Some(2.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"program_transactions_total{code="R",endpoint="mock",program="Raydium"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_duration_milliseconds_count{endpoint="mock"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"message_transport_latency_milliseconds_count{endpoint="mock",update_type="transaction"}"#
+        ),
+        Some(3.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_stage_interval_milliseconds_count{endpoint="mock",from="processed",to="confirmed"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_tx_relative_to_stage_milliseconds_count{edge="last",endpoint="mock",stage="processed"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn dead_slot_is_summarized() {
+    let server = MockGeyser::start(vec![vec![
+        transaction(7, 1, &[RAYDIUM]),
+        slot_update(7, SlotStatus::SlotDead),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&["-e", &server.endpoint()]).await;
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_DEAD slot:7"))
+        .await;
+    assert!(summary.contains("creator:transaction"), "{summary}");
+    assert!(summary.contains("no_status_yet:1"), "{summary}");
+}
+
+#[tokio::test]
+async fn reconnect_resumes_from_in_flight_slot_without_double_counting() {
+    let server = MockGeyser::start(vec![
+        vec![
+            slot_update(200, SlotStatus::SlotProcessed),
+            transaction(200, 1, &[RAYDIUM]),
+            Step::Fail(Status::unavailable("provider hiccup")),
+        ],
+        vec![
+            // Сервер повторяет все, начиная с from_slot
+            slot_update(200, SlotStatus::SlotProcessed),
+            transaction(200, 1, &[RAYDIUM]),
+            transaction(200, 2, &[RAYDIUM]),
+            slot_update(200, SlotStatus::SlotFinalized),
+        ],
+    ])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--reconnect-initial-backoff-ms",
+        "10",
+    ])
+    .await;
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:200"))
+        .await;
+    assert!(summary.contains("total_txs:2"), "{summary}");
+    // Повтор транзакции после переподключения виден как дубликат
+    assert!(summary.contains("duplicate_txs:1"), "{summary}");
+    let metrics = monitor.metrics().await;
+    assert!(
+        metrics.lines().any(
+            |line| line.starts_with("slot_duplicate_transactions_total{") && line.ends_with(" 1")
+        ),
+        "{metrics}"
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].from_slot, None);
+    assert_eq!(requests[1].from_slot, Some(200));
+}
+
+#[tokio::test]
+async fn gives_up_after_max_reconnect_attempts() {
+    let server = MockGeyser::start(vec![
+        vec![Step::Fail(Status::unavailable("down"))],
+        vec![Step::Fail(Status::unavailable("down"))],
+        vec![Step::Fail(Status::unavailable("down"))],
+    ])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--reconnect-initial-backoff-ms",
+        "10",
+        "--max-reconnect-attempts",
+        "2",
+    ])
+    .await;
+
+    assert_eq!(monitor.wait_for_exit().await, Some(1));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn recorded_stream_replays_to_the_same_summary() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(300, SlotStatus::SlotProcessed),
+        transaction(300, 1, &[RAYDIUM]),
+        transaction(300, 2, &[WHIRLPOOL]),
+        slot_update(300, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let dir = std::env::temp_dir().join(format!("grpc-connect-test-replay-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("live={}", server.endpoint()),
+        "--record",
+        dir.to_str().unwrap(),
+        "--subscribe",
+        "blocks_meta",
+    ])
+    .await;
+    let live = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:300"))
+        .await;
+    assert!(live.contains("block_meta:[missing]"), "{live}");
+    // Дожидаемся, пока писатель сбросит буфер на диск
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    drop(monitor);
+
+    // Подписку на blocks_meta при воспроизведении указываем так же, как при записи
+    let mut replay = Monitor::spawn(&[
+        "--replay",
+        dir.to_str().unwrap(),
+        "--subscribe",
+        "blocks_meta",
+    ])
+    .await;
+    // Сводка идет в stdout, итог воспроизведения в stderr, порядок между ними не гарантирован
+    let mut replayed = None;
+    let mut finished = None;
+    while replayed.is_none() || finished.is_none() {
+        let line = replay
+            .wait_for_line(|line| {
+                line.starts_with("SLOT_FINALIZED slot:300") || line.starts_with("Replay finished")
+            })
+            .await;
+        if line.starts_with("Replay finished") {
+            finished = Some(line);
+        } else {
+            replayed = Some(line);
+        }
+    }
+    assert_eq!(replayed.unwrap(), live);
+    let finished = finished.unwrap();
+    assert!(
+        finished.starts_with("Replay finished: 4 updates from 1 endpoints, 0 failed to process"),
+        "{finished}"
+    );
+    assert_eq!(replay.wait_for_exit().await, Some(0));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn abandoned_slot_is_evicted_by_distance() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(10, SlotStatus::SlotProcessed),
+        transaction(10, 1, &[RAYDIUM]),
+        slot_update(20, SlotStatus::SlotProcessed),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--evict-max-slot-distance",
+        "5",
+    ])
+    .await;
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_EVICTED slot:10"))
+        .await;
+    assert!(summary.contains("total_txs:1"), "{summary}");
+
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_trackers_evicted_total{endpoint="mock",reason="distance"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(&metrics, r#"slot_trackers_live{endpoint="mock"}"#),
+        Some(1.0)
+    );
+    // Вытесненный слот не попадает в гистограмму длительности завершенных слотов
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_duration_milliseconds_count{endpoint="mock"}"#
+        ),
+        None
+    );
+}
+
+#[tokio::test]
+async fn legacy_metric_names_can_be_disabled() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(400, SlotStatus::SlotProcessed),
+        transaction(400, 1, &[RAYDIUM]),
+        slot_update(400, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--no-legacy-metrics",
+    ])
+    .await;
+
+    monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:400"))
+        .await;
+
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_transactions_total{endpoint="mock",status="processed"}"#
+        ),
+        Some(1.0)
+    );
+    assert!(
+        !metrics.contains("slot_transactions_processed"),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+async fn json_summaries_are_written_to_file() {
+    let server = MockGeyser::start(vec![vec![
+        transaction(600, 1, &[RAYDIUM]),
+        slot_update(600, SlotStatus::SlotProcessed),
+        transaction(600, 2, &[RAYDIUM, WHIRLPOOL]),
+        slot_update(600, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let path = std::env::temp_dir().join(format!(
+        "grpc-connect-test-summaries-{}.jsonl",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let _monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--output-format",
+        "json",
+        "--output-file",
+        path.to_str().unwrap(),
+    ])
+    .await;
+
+    let line = eventually(|| {
+        let contents = std::fs::read_to_string(&path).ok()?;
+        contents.lines().next().map(str::to_string)
+    })
+    .await;
+    let summary: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(summary["outcome"], "finalized");
+    assert_eq!(summary["eviction_reason"], serde_json::Value::Null);
+    assert_eq!(summary["endpoint"], "mock");
+    assert_eq!(summary["slot"], 600);
+    assert_eq!(summary["creator"], "transaction");
+    assert_eq!(summary["total_txs"], 2);
+    assert_eq!(summary["tx_by_status"]["no_status_yet"], 1);
+    assert_eq!(summary["tx_by_status"]["processed"], 1);
+    let raydium = summary["tx_by_program"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|program| program["code"] == "R")
+        .unwrap();
+    assert_eq!(raydium["program"], "Raydium");
+    assert_eq!(raydium["tx_count"], 2);
+    assert_eq!(summary["timeline"][1]["status"], "finalized");
+    assert!(summary["first_tx_at_us"].as_u64().is_some(), "{line}");
+    assert_eq!(summary["duplicate_txs"], 0);
+    // Сигнатуры в сводку попадают только с --summary-signatures
+    assert!(summary.get("signatures").is_none(), "{line}");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn observed_transactions_are_checked_against_block_meta() {
+    let server = MockGeyser::start(vec![vec![
+        slot_update(800, SlotStatus::SlotProcessed),
+        transaction(800, 1, &[RAYDIUM]),
+        transaction(800, 2, &[WHIRLPOOL]),
+        block_meta(800, 1200),
+        slot_update(800, SlotStatus::SlotFinalized),
+        // Слот без block meta
+        slot_update(801, SlotStatus::SlotProcessed),
+        slot_update(801, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--subscribe",
+        "blocks_meta",
+    ])
+    .await;
+
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:800"))
+        .await;
+    assert!(
+        summary.contains(
+            "block_meta:[height:790 executed_txs:1200 unobserved_txs:1198 filters:amm_transactions]"
+        ),
+        "{summary}"
+    );
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:801"))
+        .await;
+    assert!(summary.contains("block_meta:[missing]"), "{summary}");
+
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_executed_transactions_total{endpoint="mock"}"#
+        ),
+        Some(1200.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"slot_block_meta_missing_total{endpoint="mock"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn endpoints_are_compared_by_arrival() {
+    let fast = MockGeyser::start(vec![vec![
+        slot_update(900, SlotStatus::SlotProcessed),
+        transaction(900, 1, &[RAYDIUM]),
+        // Этого слота у второго эндпоинта нет
+        slot_update(901, SlotStatus::SlotProcessed),
+        slot_update(900, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let slow = MockGeyser::start(vec![vec![
+        Step::Sleep(Duration::from_millis(300)),
+        slot_update(900, SlotStatus::SlotProcessed),
+        transaction(900, 1, &[RAYDIUM]),
+        slot_update(900, SlotStatus::SlotFinalized),
+    ]])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("fast={}", fast.endpoint()),
+        "-e",
+        &format!("slow={}", slow.endpoint()),
+    ])
+    .await;
+
+    monitor
+        .wait_for_line(|line| {
+            line.starts_with("SLOT_FINALIZED slot:900") && line.ends_with("endpoint:slow")
+        })
+        .await;
+
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_first_slot_status_total{endpoint="fast",status="processed"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_first_slot_status_total{endpoint="slow",status="processed"}"#
+        ),
+        None
+    );
+    // Пропущенный слот 901 не попадает в отставание
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_slot_status_lag_milliseconds_count{endpoint="slow",status="processed"}"#
+        ),
+        Some(1.0)
+    );
+    let lag = metric_value(
+        &metrics,
+        r#"endpoint_slot_status_lag_milliseconds_sum{endpoint="slow",status="processed"}"#,
+    )
+    .unwrap();
+    assert!(lag >= 100.0, "{lag}");
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_first_transaction_total{endpoint="fast"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_first_transaction_total{endpoint="slow"}"#
+        ),
+        None
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"endpoint_transaction_lag_milliseconds_count{endpoint="slow"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn too_many_endpoints_are_rejected() {
+    let specs: Vec<String> = (0..65)
+        .map(|i| format!("e{i}=http://127.0.0.1:1"))
+        .collect();
+    let args: Vec<&str> = specs
+        .iter()
+        .flat_map(|spec| ["-e", spec.as_str()])
+        .collect();
+    let mut monitor = Monitor::spawn(&args).await;
+
+    monitor
+        .wait_for_line(|line| line.contains("got 65 endpoints, at most 64"))
+        .await;
+    assert_ne!(monitor.wait_for_exit().await, Some(0));
+}
+
+#[tokio::test]
+async fn duplicate_endpoint_names_are_rejected() {
+    // Без NAME= оба эндпоинта получили бы имя хоста
+    let mut monitor =
+        Monitor::spawn(&["-e", "http://127.0.0.1:1", "-e", "http://127.0.0.1:1/other"]).await;
+
+    monitor
+        .wait_for_line(|line| line.contains("endpoint name 127.0.0.1:1 is used more than once"))
+        .await;
+    assert_ne!(monitor.wait_for_exit().await, Some(0));
+}
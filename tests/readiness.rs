@@ -0,0 +1,77 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, Step};
+use common::{Monitor, slot_update};
+use std::time::Duration;
+use tonic::Status;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+/// Polls `path` until the response satisfies `check`.
+async fn wait_for_response(
+    monitor: &Monitor,
+    path: &str,
+    check: impl Fn(u16, &str) -> bool,
+) -> (u16, String) {
+    for _ in 0..100 {
+        let (status, body) = monitor.http_get(path).await;
+        if check(status, &body) {
+            return (status, body);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("{path} never returned the expected response");
+}
+
+#[tokio::test]
+async fn ready_until_the_stream_goes_silent() {
+    let server = MockGeyser::start(vec![vec![slot_update(900, SlotStatus::SlotProcessed)]]).await;
+    let monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--ping-interval-ms",
+        "0",
+        "--stall-timeout-ms",
+        "0",
+        "--ready-max-silence-ms",
+        "1000",
+    ])
+    .await;
+
+    let (_, body) = wait_for_response(&monitor, "/status", |_, body| {
+        body.contains(r#""state":"receiving""#)
+    })
+    .await;
+    assert!(body.contains(r#""endpoint":"mock""#), "{body}");
+    assert!(body.contains(r#""last_slot":900"#), "{body}");
+    assert!(body.contains(r#""ready":true"#), "{body}");
+    assert_eq!(monitor.http_get("/ready").await.0, 200);
+
+    wait_for_response(&monitor, "/ready", |status, _| status == 503).await;
+    let (_, body) = monitor.http_get("/status").await;
+    assert!(body.contains(r#""state":"stalled""#), "{body}");
+    assert!(body.contains(r#""ready":false"#), "{body}");
+    // Liveness не зависит от стрима
+    assert_eq!(monitor.http_get("/health").await.0, 200);
+}
+
+#[tokio::test]
+async fn not_ready_while_disconnected() {
+    let server =
+        MockGeyser::start(vec![vec![Step::Fail(Status::unavailable("provider down"))]]).await;
+    let monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--reconnect-initial-backoff-ms",
+        "60000",
+        "--reconnect-max-backoff-ms",
+        "60000",
+    ])
+    .await;
+
+    let (_, body) = wait_for_response(&monitor, "/status", |_, body| {
+        body.contains(r#""state":"disconnected""#)
+    })
+    .await;
+    assert!(body.contains("provider down"), "{body}");
+    assert_eq!(monitor.http_get("/ready").await.0, 503);
+}
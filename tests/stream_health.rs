@@ -0,0 +1,207 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, Step};
+use common::{Monitor, RAYDIUM, metric_value, slot_update, transaction};
+use std::time::Duration;
+use tonic::Status;
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+#[tokio::test]
+async fn stream_health_is_exported() {
+    let server = MockGeyser::start(vec![
+        vec![
+            slot_update(100, SlotStatus::SlotProcessed),
+            slot_update(99, SlotStatus::SlotConfirmed),
+            Step::Fail(Status::unavailable("node restarting")),
+        ],
+        vec![
+            slot_update(101, SlotStatus::SlotProcessed),
+            slot_update(70, SlotStatus::SlotFinalized),
+        ],
+    ])
+    .await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--ping-interval-ms",
+        "0",
+        "--reconnect-initial-backoff-ms",
+        "10",
+    ])
+    .await;
+
+    monitor
+        .wait_for_line(|line| line.contains("Reconnecting in"))
+        .await;
+    monitor
+        .wait_for_line(|line| line.contains("Listening for updates"))
+        .await;
+
+    // Метрики второй сессии появляются чуть позже строки о подписке
+    let mut metrics = String::new();
+    for _ in 0..100 {
+        metrics = monitor.metrics().await;
+        if metric_value(
+            &metrics,
+            r#"stream_highest_slot{endpoint="mock",status="finalized"}"#,
+        ) == Some(70.0)
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_messages_total{endpoint="mock",update_type="slot"}"#
+        ),
+        Some(4.0)
+    );
+    assert!(
+        metric_value(&metrics, r#"stream_received_bytes_total{endpoint="mock"}"#)
+            .is_some_and(|bytes| bytes > 0.0),
+        "{metrics}"
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_errors_total{code="unavailable",endpoint="mock"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(&metrics, r#"stream_reconnects_total{endpoint="mock"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_highest_slot{endpoint="mock",status="processed"}"#
+        ),
+        Some(101.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_slots_behind_processed{endpoint="mock",status="finalized"}"#
+        ),
+        Some(31.0)
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_slots_behind_processed{endpoint="mock",status="confirmed"}"#
+        ),
+        Some(2.0)
+    );
+    // Интервал между сообщениями не включает паузу на переподключение
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"stream_message_interval_milliseconds_count{endpoint="mock"}"#
+        ),
+        Some(2.0)
+    );
+}
+
+#[tokio::test]
+async fn updates_pass_through_processing_queue() {
+    let server = MockGeyser::start(vec![burst(700)]).await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--processing-queue-size",
+        "1",
+        "--ping-interval-ms",
+        "0",
+    ])
+    .await;
+
+    // Очередь на одно сообщение тормозит чтение, но ничего не теряет
+    monitor
+        .wait_for_line(|line| {
+            line.starts_with("SLOT_FINALIZED slot:700") && line.contains("total_txs:200")
+        })
+        .await;
+    let metrics = monitor.metrics().await;
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"processing_queue_delay_milliseconds_count{endpoint="mock"}"#
+        ),
+        Some(202.0)
+    );
+    assert!(
+        metric_value(&metrics, r#"processing_queue_depth{endpoint="mock"}"#).is_some(),
+        "{metrics}"
+    );
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"processing_queue_dropped_total{endpoint="mock"}"#
+        ),
+        None
+    );
+    let blocked = metric_value(
+        &metrics,
+        r#"processing_queue_blocked_total{endpoint="mock"}"#,
+    );
+    assert!(blocked.is_some_and(|blocked| blocked > 0.0), "{metrics}");
+}
+
+#[tokio::test]
+async fn full_processing_queue_drops_updates_when_asked() {
+    let server = MockGeyser::start(vec![burst(710)]).await;
+    let monitor = Monitor::spawn(&[
+        "-e",
+        &format!("mock={}", server.endpoint()),
+        "--processing-queue-size",
+        "1",
+        "--drop-when-queue-full",
+        "--ping-interval-ms",
+        "0",
+    ])
+    .await;
+
+    // Каждое сообщение либо обработано, либо отброшено, чтение не ждет
+    let settled = async {
+        loop {
+            let metrics = monitor.metrics().await;
+            let value = |name: &str| {
+                metric_value(&metrics, &format!(r#"{name}{{endpoint="mock"}}"#)).unwrap_or(0.0)
+            };
+            if value("processing_queue_delay_milliseconds_count")
+                + value("processing_queue_dropped_total")
+                == 202.0
+            {
+                return metrics;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    };
+    let metrics = tokio::time::timeout(Duration::from_secs(15), settled)
+        .await
+        .expect("timed out waiting for the queue to settle");
+    let dropped = metric_value(
+        &metrics,
+        r#"processing_queue_dropped_total{endpoint="mock"}"#,
+    );
+    assert!(dropped.is_some_and(|dropped| dropped > 0.0), "{metrics}");
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"processing_queue_blocked_total{endpoint="mock"}"#
+        ),
+        None
+    );
+}
+
+/// 200 transactions of `slot` between its processed and finalized updates,
+/// sent without pauses.
+fn burst(slot: u64) -> Vec<Step> {
+    let mut updates: Vec<Step> = (0..200).map(|i| transaction(slot, i, &[RAYDIUM])).collect();
+    updates.insert(0, slot_update(slot, SlotStatus::SlotProcessed));
+    updates.push(slot_update(slot, SlotStatus::SlotFinalized));
+    updates
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::mock_geyser::MockGeyser;
+use common::{Monitor, RAYDIUM, WHIRLPOOL};
+use yellowstone_grpc_proto::geyser::CommitmentLevel;
+
+#[tokio::test]
+async fn config_file_and_flags_shape_the_subscribe_request() {
+    let server = MockGeyser::start(vec![vec![]]).await;
+    let config = std::env::temp_dir().join(format!(
+        "grpc-connect-test-subscription-{}.toml",
+        std::process::id()
+    ));
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+commitment = "processed"
+subscribe = ["blocks_meta"]
+
+[transactions]
+failed = true
+account_exclude = ["{WHIRLPOOL}"]
+
+[slots]
+interslot_updates = false
+"#
+        ),
+    )
+    .unwrap();
+
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.endpoint(),
+        "--enable-program",
+        "R",
+        "--subscription-config",
+        config.to_str().unwrap(),
+        "--commitment",
+        "finalized",
+        "--subscribe",
+        "entries",
+        "--account-required",
+        RAYDIUM,
+    ])
+    .await;
+    monitor
+        .wait_for_line(|line| line.ends_with("Listening for updates..."))
+        .await;
+    std::fs::remove_file(&config).unwrap();
+
+    let requests = server.requests();
+    let request = &requests[0];
+    assert_eq!(request.commitment, Some(CommitmentLevel::Finalized as i32));
+    assert!(request.blocks_meta.contains_key("blocks_meta"));
+    assert!(request.entry.contains_key("entries"));
+    assert!(request.accounts.is_empty());
+    assert!(request.transactions_status.is_empty());
+
+    let transactions = &request.transactions["amm_transactions"];
+    assert_eq!(transactions.vote, Some(false));
+    assert_eq!(transactions.failed, Some(true));
+    assert!(transactions.account_include.contains(&RAYDIUM.to_string()));
+    assert_eq!(transactions.account_exclude, vec![WHIRLPOOL.to_string()]);
+    assert_eq!(transactions.account_required, vec![RAYDIUM.to_string()]);
+
+    let slots = &request.slots["client"];
+    assert_eq!(slots.filter_by_commitment, Some(false));
+    assert_eq!(slots.interslot_updates, Some(false));
+}
+
+#[tokio::test]
+async fn accounts_subscription_without_filter_is_rejected() {
+    let mut monitor = Monitor::spawn(&["--subscribe", "accounts"]).await;
+
+    monitor
+        .wait_for_line(|line| line.contains("accounts subscription needs at least one account"))
+        .await;
+    assert_eq!(monitor.wait_for_exit().await, Some(1));
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use common::mock_geyser::{MockGeyser, MockGeyserHandle};
+use common::{Monitor, RAYDIUM, slot_update, transaction};
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+use std::path::PathBuf;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+use yellowstone_grpc_proto::geyser::SlotStatus;
+
+const SERVER_NAME: &str = "mock.geyser.test";
+
+/// A throwaway CA with a server and a client certificate, written as PEM
+/// files into a temporary directory.
+struct TestPki {
+    dir: PathBuf,
+    ca_pem: String,
+    server_cert_pem: String,
+    server_key_pem: String,
+}
+
+impl TestPki {
+    fn generate(test: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "grpc-connect-test-tls-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server_cert = CertificateParams::new(vec![SERVER_NAME.to_string()])
+            .unwrap()
+            .signed_by(&server_key, &ca)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let client_cert = CertificateParams::new(vec!["monitor".to_string()])
+            .unwrap()
+            .signed_by(&client_key, &ca)
+            .unwrap();
+
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("client.pem"), client_cert.pem()).unwrap();
+        std::fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+
+        Self {
+            dir,
+            ca_pem: ca.pem(),
+            server_cert_pem: server_cert.pem(),
+            server_key_pem: server_key.serialize_pem(),
+        }
+    }
+
+    fn path(&self, file: &str) -> String {
+        self.dir.join(file).to_str().unwrap().to_string()
+    }
+
+    fn server_tls(&self, require_client_cert: bool) -> ServerTlsConfig {
+        let tls = ServerTlsConfig::new().identity(Identity::from_pem(
+            &self.server_cert_pem,
+            &self.server_key_pem,
+        ));
+        if require_client_cert {
+            tls.client_ca_root(Certificate::from_pem(&self.ca_pem))
+        } else {
+            tls
+        }
+    }
+
+    async fn start_server(&self, require_client_cert: bool) -> MockGeyserHandle {
+        MockGeyser::start_with_tls(
+            vec![vec![
+                slot_update(500, SlotStatus::SlotProcessed),
+                transaction(500, 1, &[RAYDIUM]),
+                slot_update(500, SlotStatus::SlotFinalized),
+            ]],
+            Some(self.server_tls(require_client_cert)),
+        )
+        .await
+    }
+}
+
+impl Drop for TestPki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+async fn assert_slot_received(monitor: &mut Monitor) {
+    let summary = monitor
+        .wait_for_line(|line| line.starts_with("SLOT_FINALIZED slot:500"))
+        .await;
+    assert!(summary.contains("total_txs:1"), "{summary}");
+}
+
+#[tokio::test]
+async fn custom_ca_and_domain_override_verify_the_server() {
+    let pki = TestPki::generate("ca");
+    let server = pki.start_server(false).await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.tls_endpoint(),
+        "--ca-cert",
+        &pki.path("ca.pem"),
+        "--tls-domain",
+        SERVER_NAME,
+    ])
+    .await;
+
+    assert_slot_received(&mut monitor).await;
+}
+
+#[tokio::test]
+async fn untrusted_certificate_is_rejected() {
+    let pki = TestPki::generate("untrusted");
+    let server = pki.start_server(false).await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.tls_endpoint(),
+        "--tls-domain",
+        SERVER_NAME,
+        "--reconnect-initial-backoff-ms",
+        "10",
+        "--max-reconnect-attempts",
+        "1",
+    ])
+    .await;
+
+    assert_eq!(monitor.wait_for_exit().await, Some(1));
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn insecure_accepts_an_untrusted_certificate() {
+    let pki = TestPki::generate("insecure");
+    let server = pki.start_server(false).await;
+    let mut monitor = Monitor::spawn(&["-e", &server.tls_endpoint(), "--insecure"]).await;
+
+    monitor
+        .wait_for_line(|line| line.contains("TLS certificate verification is disabled"))
+        .await;
+    assert_slot_received(&mut monitor).await;
+}
+
+#[tokio::test]
+async fn client_certificate_is_presented_for_mutual_tls() {
+    let pki = TestPki::generate("mtls");
+    let server = pki.start_server(true).await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.tls_endpoint(),
+        "--ca-cert",
+        &pki.path("ca.pem"),
+        "--tls-domain",
+        SERVER_NAME,
+        "--client-cert",
+        &pki.path("client.pem"),
+        "--client-key",
+        &pki.path("client.key"),
+    ])
+    .await;
+
+    assert_slot_received(&mut monitor).await;
+}
+
+#[tokio::test]
+async fn insecure_mode_also_presents_the_client_certificate() {
+    let pki = TestPki::generate("insecure-mtls");
+    let server = pki.start_server(true).await;
+    let mut monitor = Monitor::spawn(&[
+        "-e",
+        &server.tls_endpoint(),
+        "--insecure",
+        "--client-cert",
+        &pki.path("client.pem"),
+        "--client-key",
+        &pki.path("client.key"),
+    ])
+    .await;
+
+    assert_slot_received(&mut monitor).await;
+}